@@ -166,7 +166,8 @@ pub fn build_move_package(
 
 /// Version of the framework code that the binary used for compilation expects should be the same as
 /// version of the framework code bundled as compiled package's dependency and this function
-/// verifies this.
+/// verifies this. A package that does not depend on the Sui framework, e.g. one built on the Move
+/// stdlib alone, only has its stdlib checked.
 fn verify_framework_version(pkg: &CompiledPackage) -> SuiResult<()> {
     // We stash compiled modules in the Modules map which is sorted so that we can compare sets of
     // compiled modules directly.
@@ -180,7 +181,7 @@ fn verify_framework_version(pkg: &CompiledPackage) -> SuiResult<()> {
     let framework_modules = Modules::new(get_sui_framework().iter()).iter_modules_owned();
     let framework: Vec<&CompiledModule> = framework_modules.iter().collect();
 
-    if dep_framework != framework {
+    if !dep_framework.is_empty() && dep_framework != framework {
         return Err(SuiError::ModuleVerificationFailure {
             error: "Sui framework version mismatch detected.\
                     Make sure that the sui command line tool and the Sui framework code\
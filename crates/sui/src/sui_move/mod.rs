@@ -41,7 +41,7 @@ pub fn execute_move_command(
         Command::Build(c) => c.execute(package_path, build_config),
         Command::Coverage(c) => c.execute(package_path, build_config),
         Command::Disassemble(c) => c.execute(package_path, build_config),
-        Command::New(c) => c.execute(package_path),
+        Command::New(c) => c.execute(package_path, build_config).map_err(Into::into),
         Command::Prove(c) => c.execute(package_path, build_config),
         Command::Test(c) => {
             let unit_test_config = UnitTestingConfig {
@@ -1,29 +1,2955 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use super::build;
+use anyhow::{anyhow, bail, Context};
+use clap::builder::PossibleValuesParser;
 use clap::Parser;
-use move_cli::base::new;
-use std::path::PathBuf;
+use move_core_types::account_address::AccountAddress;
+use move_core_types::identifier;
+use move_package::BuildConfig;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+#[cfg(test)]
+#[path = "../unit_tests/move_new_tests.rs"]
+mod move_new_tests;
 
 const SUI_PKG_NAME: &str = "Sui";
-const SUI_PKG_PATH: &str = "{ git = \"https://github.com/MystenLabs/sui.git\", subdir = \"crates/sui-framework\", rev = \"main\" }";
+const SUI_GIT_URL: &str = "https://github.com/MystenLabs/sui.git";
+const SUI_FRAMEWORK_SUBDIR: &str = "crates/sui-framework";
+const STDLIB_PKG_NAME: &str = "MoveStdlib";
+const STDLIB_SUBDIR: &str = "crates/sui-framework/deps/move-stdlib";
+
+const MANIFEST_FILE: &str = "Move.toml";
+const SOURCES_DIR: &str = "sources";
+const TESTS_DIR: &str = "tests";
+const EXAMPLES_DIR: &str = "examples";
+const GITIGNORE_FILE: &str = ".gitignore";
+const LICENSE_FILE: &str = "LICENSE";
+const LOCK_FILE: &str = "Move.lock";
+const FORMAT_CONFIG_FILE: &str = "move-fmt.toml";
+const SCAFFOLD_RECORD_FILE: &str = ".sui-scaffold.json";
+const README_FILE: &str = "README.md";
+
+/// Environment variables overriding the default git repository and revision of the framework.
+const FRAMEWORK_GIT_ENV: &str = "SUI_FRAMEWORK_GIT";
+const FRAMEWORK_REV_ENV: &str = "SUI_FRAMEWORK_REV";
 
-#[derive(Parser)]
+const DEFAULT_PACKAGE_VERSION: &str = "0.0.1";
+/// Location of the `sui move new` defaults, relative to the home directory.
+const CONFIG_FILE: &str = ".sui/move-new.toml";
+
+#[derive(Parser, Clone)]
 pub struct New {
     #[clap(flatten)]
-    pub new: new::New,
+    pub spec: ScaffoldSpec,
+    /// Print the files that would be created instead of writing them.
+    #[clap(long = "dry-run")]
+    pub dry_run: bool,
+    /// Overwrite the files of an existing package at the target path, warning about
+    /// each one. Without it, a directory already holding a `Move.toml` or a
+    /// `sources/` directory is left untouched and the command fails.
+    #[clap(long = "overwrite")]
+    pub overwrite: bool,
+    /// Print a JSON summary of the generated package instead of human-readable output.
+    /// Prompts go to stderr, so that stdout only holds the JSON. Only a single
+    /// package can be created with it.
+    #[clap(long = "json")]
+    pub json: bool,
+    /// Do not list the created files. Warnings and errors are still reported on stderr.
+    #[clap(long = "quiet")]
+    pub quiet: bool,
+    /// File to read defaults for these options from. Defaults to
+    /// `~/.sui/move-new.toml`.
+    #[clap(long = "config", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+    /// Pin the framework to the commit its revision resolves to: the manifest
+    /// depends on that commit, so the package builds against the same framework
+    /// everywhere, and a `Move.lock` records the revision it was resolved from.
+    /// Skipped with a warning if the revision cannot be resolved, e.g. offline.
+    #[clap(long = "lock")]
+    pub lock: bool,
+    /// Prompt for the package name, template, edition and framework revision.
+    #[clap(long = "interactive")]
+    pub interactive: bool,
+    /// Build the package once it is generated, failing if it does not compile
+    /// against the framework it depends on. The generated files are kept either way.
+    #[clap(long = "verify", conflicts_with = "dry-run")]
+    pub verify: bool,
+    /// List the built-in templates and exit, as a JSON array with `--json`.
+    #[clap(long = "list-templates")]
+    pub list_templates: bool,
+    /// Print a completion script for `sui move new` and exit, e.g. to
+    /// `source <(sui move new --completions bash)`. It completes flags, and the
+    /// values of `--template`, `--edition`, `--license` and the like from the
+    /// ones this CLI knows about. Only bash is supported. Other `sui` commands
+    /// keep the completion registered before the script is sourced.
+    #[clap(
+        long = "completions",
+        value_name = "SHELL",
+        value_parser = PossibleValuesParser::new(["bash"])
+    )]
+    pub completions: Option<String>,
+    /// Print only the absolute path of the created package on stdout, e.g. to
+    /// `cd "$(sui move new --print-path pkg)"`. Prompts and diagnostics go to stderr.
+    /// Only a single package can be created with it.
+    #[clap(long = "print-path", conflicts_with_all = &["json", "dry-run", "verify"])]
+    pub print_path: bool,
+    /// Do not warn when the framework revision does not match the version of this CLI.
+    #[clap(long = "allow-version-mismatch")]
+    pub allow_version_mismatch: bool,
+    /// Only create the files of the package that are missing, keeping existing ones
+    /// as they are, so that the command can be re-run safely.
+    #[clap(long = "sync", conflicts_with = "overwrite")]
+    pub sync: bool,
+    /// When creating several packages, stop at the first one that fails instead
+    /// of reporting the failure and moving on to the next.
+    #[clap(long = "fail-fast")]
+    pub fail_fast: bool,
+    /// Print a hash of the paths and contents of the generated files, to check that
+    /// regenerating the package gives the same output. With `--dry-run`, only the
+    /// hash is printed.
+    #[clap(long = "manifest-sha", conflicts_with = "print-path")]
+    pub manifest_sha: bool,
+}
+
+/// The options describing a package to scaffold, independently of how it is
+/// written out. Used by `sui move new`, and by tools generating packages with
+/// [`scaffold`].
+#[derive(Parser, Clone, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScaffoldSpec {
+    /// The name of the package to be created. Prompted for if missing and
+    /// running in a terminal.
+    pub name: Option<String>,
+    /// Names of the member packages to create in `--workspace` mode. Otherwise,
+    /// more packages to create next to the first one, with the same options: each
+    /// package is then created in a directory named after it under `path`.
+    #[clap(value_name = "MEMBERS")]
+    pub members: Vec<String>,
+    /// Create a workspace: a top-level package holding the framework
+    /// dependency, with each of `MEMBERS` scaffolded in its own subdirectory
+    /// and depending on it.
+    #[clap(long = "workspace", conflicts_with = "module-name")]
+    pub workspace: bool,
+    /// Git revision (commit, tag or branch) of the Sui framework to depend on.
+    /// Defaults to `$SUI_FRAMEWORK_REV` if set, or else to the release tag
+    /// matching this CLI's version; pass `main` to track the tip of the Sui
+    /// repository.
+    #[clap(long = "framework-rev")]
+    pub framework_rev: Option<String>,
+    /// Directory of the framework package within its git repository, relative
+    /// to the repository root. Defaults to `crates/sui-framework`.
+    #[clap(long = "framework-subdir", conflicts_with = "local-framework")]
+    pub framework_subdir: Option<String>,
+    /// Depend on the Move standard library only, as bundled in the Sui
+    /// repository, instead of the Sui framework. Only the `basic` template can
+    /// be used. Same as `--flavor core`.
+    #[clap(
+        long = "no-framework",
+        conflicts_with_all = &[
+            "framework-rev",
+            "local-framework",
+            "framework-subdir",
+            "framework-dep-name",
+            "template-git",
+            "examples",
+        ]
+    )]
+    pub no_framework: bool,
+    /// Move environment to target, choosing the framework the package depends on
+    /// and its default named addresses. Defaults to `sui`.
+    #[clap(
+        long = "flavor",
+        value_parser = PossibleValuesParser::new(flavor_names()),
+        conflicts_with = "no-framework"
+    )]
+    pub flavor: Option<String>,
+    /// Git repository to take the framework from, from `$SUI_FRAMEWORK_GIT`.
+    /// Defaults to the Sui repository.
+    #[clap(skip)]
+    pub framework_git: Option<String>,
+    /// Path to a local checkout of the Sui repository to take the framework
+    /// from, instead of fetching it from git.
+    #[clap(
+        long = "local-framework",
+        parse(from_os_str),
+        conflicts_with = "framework-rev"
+    )]
+    pub local_framework: Option<PathBuf>,
+    /// Key of the framework in the `[dependencies]` section of the manifest.
+    /// Defaults to `Sui`; the name must match the framework package's own name
+    /// for the package to build.
+    #[clap(long = "framework-dep-name")]
+    pub framework_dep_name: Option<String>,
+    /// Scaffold the starter module from a built-in template, as listed by
+    /// `--list-templates`. Defaults to an empty `basic` module.
+    #[clap(long = "template", value_parser = PossibleValuesParser::new(template_names()))]
+    pub template: Option<String>,
+    /// Scaffold the package from the template repository at this git URL
+    /// instead. The template's own `Move.toml` is used, and placeholders in its
+    /// text files and file names are substituted: `{{package_name}}`,
+    /// `{{module_name}}`, `{{address_name}}`, `{{author}}` and
+    /// `{{addresses.<NAME>}}` for each `--named-address`. A placeholder without
+    /// value is an error. Options of the generated manifest, such as the framework
+    /// and the package version, cannot be passed: they are the template's.
+    #[clap(
+        long = "template-git",
+        conflicts_with_all = &[
+            "template",
+            "workspace",
+            "flavor",
+            "framework-rev",
+            "local-framework",
+            "framework-subdir",
+            "framework-dep-name",
+            "version",
+            "edition",
+            "description",
+            "with-tests",
+            "no-tests",
+            "ci",
+            "lock",
+        ]
+    )]
+    pub template_git: Option<String>,
+    /// Git revision (commit, tag or branch) of the `--template-git` repository
+    /// to use. Defaults to its default branch.
+    #[clap(long = "template-rev", requires = "template-git")]
+    pub template_rev: Option<String>,
+    /// Also generate small standalone example modules, with their tests, under
+    /// `examples/`. They are built and tested in dev mode, with `--dev`.
+    #[clap(long = "examples")]
+    pub examples: bool,
+    /// Also generate a `README.md` on building and testing the package, and the
+    /// framework it depends on. An existing one is kept unless `--overwrite` is passed.
+    #[clap(long = "with-readme", conflicts_with = "template-git")]
+    pub with_readme: bool,
+    /// Generate the `README.md` from the template at `PATH` instead of the
+    /// built-in one. Implies `--with-readme`. The `{{package_name}}`,
+    /// `{{module_name}}`, `{{address_name}}`, `{{framework_dep_name}}`,
+    /// `{{framework}}`, `{{framework_rev}}` and `{{description}}` placeholders
+    /// are replaced by their values.
+    #[clap(
+        long = "readme-template",
+        value_name = "PATH",
+        parse(from_os_str),
+        conflicts_with = "template-git"
+    )]
+    pub readme_template: Option<PathBuf>,
+    /// Generate a starter unit test module under `tests/` (the default).
+    #[clap(long = "with-tests", overrides_with = "no-tests")]
+    pub with_tests: bool,
+    /// Do not generate a starter unit test module.
+    #[clap(long = "no-tests", overrides_with = "with-tests")]
+    pub no_tests: bool,
+    /// Version of the package, in `MAJOR.MINOR.PATCH` form. Defaults to `0.0.1`.
+    #[clap(long = "version")]
+    #[serde(rename = "version")]
+    pub package_version: Option<String>,
+    /// Add an entry to the `[addresses]` section of the manifest, as
+    /// `NAME=VALUE`. May be repeated. The package's own address defaults to
+    /// `0x0` unless given here.
+    #[clap(long = "named-address", multiple_occurrences = true)]
+    #[serde(rename = "named-address")]
+    pub named_addresses: Vec<String>,
+    /// Add an entry to the `[dev-addresses]` section of the manifest, as
+    /// `NAME=VALUE`. May be repeated. Used instead of `[addresses]` in dev mode,
+    /// e.g. to test against a deterministic address.
+    #[clap(
+        long = "dev-address",
+        multiple_occurrences = true,
+        conflicts_with = "template-git"
+    )]
+    #[serde(rename = "dev-address")]
+    pub dev_addresses: Vec<String>,
+    /// Allow `--named-address` and `--dev-address` to assign names the framework
+    /// already uses, such as `sui` or `std`.
+    #[clap(long = "allow-reserved-address")]
+    pub allow_reserved_address: bool,
+    /// TOML fragment to merge into the generated `Move.toml`, e.g. to add shared
+    /// dependencies or custom sections. Its entries are added to the sections of
+    /// the manifest, and take precedence over generated entries of the same key.
+    #[clap(
+        long = "manifest-extra",
+        value_name = "PATH",
+        parse(from_os_str),
+        conflicts_with = "template-git"
+    )]
+    pub manifest_extra: Option<PathBuf>,
+    /// Depend on the local package at `PATH`, relative to the new package, under
+    /// `NAME` in `[dependencies]`, as `NAME=PATH`. May be repeated. In
+    /// `--workspace` mode, the dependencies are added to the workspace package.
+    #[clap(
+        long = "depends-on",
+        value_name = "NAME=PATH",
+        multiple_occurrences = true,
+        conflicts_with = "template-git"
+    )]
+    pub depends_on: Vec<String>,
+    /// Human-readable description of the package, added to its manifest.
+    #[clap(long = "description")]
+    pub description: Option<String>,
+    /// Move edition of the package. The `legacy` edition used by the bundled
+    /// framework is implied when omitted.
+    #[clap(long = "edition", value_parser = PossibleValuesParser::new(EDITIONS.iter().copied()))]
+    pub edition: Option<String>,
+    /// SPDX identifier of the package's license, or `none`. Writes a `LICENSE`
+    /// file, crediting the `--author` if any, and an SPDX header in the
+    /// generated Move sources.
+    #[clap(long = "license", value_parser = PossibleValuesParser::new(license_ids()))]
+    pub license: Option<String>,
+    /// Copyright holder to credit in the generated Move sources and license.
+    #[clap(long = "author")]
+    pub author: Option<String>,
+    /// Year of the copyright notice. Defaults to the current year, in UTC.
+    #[clap(long = "year", requires = "author")]
+    pub year: Option<u32>,
+    /// Generate a CI workflow building and testing the package against its
+    /// framework revision. Defaults to none.
+    #[clap(long = "ci", value_parser = PossibleValuesParser::new(ci_provider_names()))]
+    pub ci: Option<String>,
+    /// Move identifier of the starter module, naming its source file, its `module`
+    /// declaration and the starter tests. Defaults to the lowercased package name,
+    /// which also names the package's address unless it isn't a valid identifier.
+    #[clap(long = "module-name")]
+    pub module_name: Option<String>,
+    /// Do not write a `.gitignore` into the package.
+    #[clap(long = "no-gitignore")]
+    pub no_gitignore: bool,
+    /// Write a `move-fmt.toml` with default formatting settings into the package.
+    /// An existing one is kept.
+    #[clap(long = "with-format-config")]
+    pub with_format_config: bool,
+    /// Only write a `Move.toml`, e.g. to turn a directory of existing sources into
+    /// a package. No sources, tests or `.gitignore` are generated.
+    #[clap(
+        long = "bare",
+        conflicts_with_all = &[
+            "workspace",
+            "template",
+            "template-git",
+            "examples",
+            "with-tests",
+            "license",
+            "ci",
+            "with-format-config",
+            "with-readme",
+            "readme-template",
+            "record-command",
+        ]
+    )]
+    pub bare: bool,
+    /// Record the options the package was generated with, once resolved, in a
+    /// `.sui-scaffold.json` file, to regenerate or audit the package later.
+    #[clap(long = "record-command")]
+    pub record_command: bool,
 }
 
 impl New {
-    pub fn execute(self, path: Option<PathBuf>) -> anyhow::Result<()> {
-        let name = &self.new.name.to_lowercase();
-        self.new.execute(
-            path,
-            "0.0.1",
-            [(SUI_PKG_NAME, SUI_PKG_PATH)],
-            [(name, "0x0")],
-            "",
+    /// Scaffolds the package at `path`, or in a directory named after the package if
+    /// `path` is not provided. An existing package is only overwritten with
+    /// `--overwrite`. `build_config` is used to build the package with `--verify`.
+    pub fn execute(self, path: Option<PathBuf>, build_config: BuildConfig) -> Result<(), NewError> {
+        self.execute_in(&Environment::current(), path, build_config)
+    }
+
+    /// Like `execute`, taking defaults from `env` instead of the environment of
+    /// this process.
+    fn execute_in(
+        self,
+        env: &Environment,
+        path: Option<PathBuf>,
+        build_config: BuildConfig,
+    ) -> Result<(), NewError> {
+        if !self.list_templates && !self.spec.workspace && !self.spec.members.is_empty() {
+            return self.execute_each(env, path, build_config);
+        }
+        self.run(env, path, build_config).map_err(NewError::from)
+    }
+
+    /// Creates each of the packages named on the command line under `path`,
+    /// reporting failures on stderr and carrying on unless `--fail-fast` is set.
+    fn execute_each(
+        self,
+        env: &Environment,
+        path: Option<PathBuf>,
+        build_config: BuildConfig,
+    ) -> Result<(), NewError> {
+        // The JSON summary and the printed path are those of one package.
+        for (set, flag) in [
+            (self.interactive, "--interactive"),
+            (self.json, "--json"),
+            (self.print_path, "--print-path"),
+        ] {
+            if set {
+                return Err(anyhow!("{} can only be used to create a single package", flag).into());
+            }
+        }
+        let parent = path.unwrap_or_default();
+        let names: Vec<_> = self
+            .spec
+            .name
+            .iter()
+            .chain(&self.spec.members)
+            .cloned()
+            .collect();
+        let mut failures = Vec::new();
+        for name in &names {
+            let mut new = self.clone();
+            new.spec.name = Some(name.clone());
+            new.spec.members.clear();
+            if let Err(e) = new.run(env, Some(parent.join(name)), build_config.clone()) {
+                let e = NewError::from(e);
+                if self.fail_fast {
+                    return Err(e);
+                }
+                eprintln!("Failed to create {}: {}", name, e);
+                failures.push((name.clone(), e));
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(NewError::PackagesFailed {
+                failures,
+                total: names.len(),
+            })
+        }
+    }
+
+    fn run(
+        mut self,
+        env: &Environment,
+        path: Option<PathBuf>,
+        build_config: BuildConfig,
+    ) -> anyhow::Result<()> {
+        if self.list_templates {
+            return list_templates(&mut io::stdout(), self.json);
+        }
+        if self.completions.is_some() {
+            return bash_completions(&mut io::stdout());
+        }
+        // Flags take precedence over the environment, which takes precedence over the config.
+        self.apply_env(env.framework_git.clone(), env.framework_rev.clone())?;
+        let config = NewConfig::load(self.config.as_deref(), env.home.as_deref())?;
+        self.apply_config(config);
+        if self.interactive || self.spec.name.is_none() {
+            let prompt_stream = if self.prompts_on_stderr() {
+                atty::Stream::Stderr
+            } else {
+                atty::Stream::Stdout
+            };
+            if !atty::is(atty::Stream::Stdin) || !atty::is(prompt_stream) {
+                if self.interactive {
+                    bail!("--interactive can only be used in a terminal");
+                }
+                return Err(NewError::MissingName.into());
+            }
+            if self.prompts_on_stderr() {
+                self.prompt_options(&mut io::stdin().lock(), &mut io::stderr())?;
+            } else {
+                self.prompt_options(&mut io::stdin().lock(), &mut io::stdout())?;
+            }
+        }
+        let package_root = match path {
+            Some(path) => path,
+            None => PathBuf::from(self.spec.package_name()?),
+        };
+        if !self.sync {
+            check_no_existing_package(&package_root, self.overwrite, self.spec.bare)?;
+            if self.spec.workspace {
+                for member in &self.spec.members {
+                    if package_root.join(member).exists() {
+                        bail!(
+                            "Cannot create workspace member {}: {} already exists",
+                            member,
+                            package_root.join(member).display()
+                        );
+                    }
+                }
+            }
+        }
+        // A package kept by --sync keeps the framework commit its Move.lock records.
+        let locked_rev = if self.lock && !(self.sync && package_root.join(LOCK_FILE).exists()) {
+            self.pin_framework_rev()?
+        } else {
+            None
+        };
+        let mut scaffold = self.generate(&package_root)?;
+        // The hash covers every generated file, whichever are then written.
+        let sha = self.manifest_sha.then(|| scaffold.sha());
+        if !self.overwrite {
+            // Unlike the package's own files, an existing README is expected.
+            scaffold.files.retain(|file| {
+                file.path != Path::new(README_FILE) || !package_root.join(README_FILE).exists()
+            });
+        }
+        // The whole package is generated first, then only the missing files are kept.
+        let mut skipped: Vec<PathBuf> = Vec::new();
+        if self.sync {
+            let (existing, missing): (Vec<_>, Vec<_>) = scaffold
+                .files
+                .into_iter()
+                .partition(|file| package_root.join(&file.path).exists());
+            scaffold.files = missing;
+            skipped.extend(existing.into_iter().map(|file| file.path));
+        }
+        if let (Some(framework), false) = (&scaffold.framework, self.allow_version_mismatch) {
+            // A pinned commit is checked as the revision it was resolved from.
+            let rev = locked_rev.as_deref().unwrap_or(&framework.rev);
+            if let Some(warning) = version_mismatch(env!("CARGO_PKG_VERSION"), rev) {
+                eprintln!("Warning: {}", warning);
+            }
+        }
+        if self.overwrite {
+            for file in &scaffold.files {
+                let path = package_root.join(&file.path);
+                if file.path != Path::new(GITIGNORE_FILE) && path.exists() {
+                    eprintln!("Warning: overwriting {}", path.display());
+                }
+            }
+        }
+        let mut created: Vec<&Path> = scaffold.files.iter().map(|f| f.path.as_path()).collect();
+        if !self.dry_run {
+            write_files(&package_root, &scaffold.files, self.spec.bare).map_err(NewError::Write)?;
+            if self.lock && self.sync && package_root.join(LOCK_FILE).exists() {
+                skipped.push(PathBuf::from(LOCK_FILE));
+            } else if let (Some(rev), Some(framework)) = (&locked_rev, &scaffold.framework) {
+                let lock = lock_file(&scaffold.framework_dep_name, framework, rev);
+                fs::write(package_root.join(LOCK_FILE), lock)
+                    .with_context(|| format!("Cannot write {}", LOCK_FILE))
+                    .map_err(NewError::Write)?;
+                created.push(Path::new(LOCK_FILE));
+            }
+        }
+
+        if self.json {
+            let summary = Summary {
+                package_root: std::env::current_dir()?.join(&package_root),
+                files: created,
+                skipped: skipped.iter().map(PathBuf::as_path).collect(),
+                framework_rev: scaffold.framework.as_ref().map(|f| f.rev.as_str()),
+                manifest_sha: sha.as_deref(),
+                named_addresses: scaffold
+                    .addresses
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect(),
+            };
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        } else if self.print_path {
+            println!("{}", fs::canonicalize(&package_root)?.display());
+        } else if let (Some(sha), true) = (&sha, self.dry_run) {
+            println!("{}", sha);
+        } else if self.dry_run {
+            for file in &scaffold.files {
+                println!("==> {} <==", package_root.join(&file.path).display());
+                match file.text() {
+                    Some(text) => println!("{}", text),
+                    None => println!("<{} bytes of binary data>", file.contents.len()),
+                }
+            }
+        } else if !self.quiet {
+            let skipped: Vec<_> = skipped.iter().map(PathBuf::as_path).collect();
+            print_created(&mut io::stdout(), &package_root, &created, &skipped)?;
+        }
+        if let (Some(sha), false, false) = (&sha, self.json, self.dry_run) {
+            println!("{}", sha);
+        }
+
+        if self.verify {
+            build::Build::execute_internal(&package_root, build_config, false)
+                .with_context(|| {
+                    format!(
+                        "The package generated at {} does not build",
+                        package_root.display()
+                    )
+                })
+                .map_err(NewError::Verify)?;
+            if !self.json && !self.quiet {
+                println!("Verified that {} builds", package_root.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether prompts are shown on stderr, as stdout is meant to be captured when
+    /// printing the path or a JSON summary.
+    fn prompts_on_stderr(&self) -> bool {
+        self.print_path || self.json
+    }
+
+    /// Resolves the framework revision to a commit for `--lock`, which the package
+    /// then depends on. Returns the revision it was resolved from, or `None` with a
+    /// warning if it can't be resolved.
+    fn pin_framework_rev(&mut self) -> anyhow::Result<Option<String>> {
+        if self.spec.local_framework.is_some() {
+            eprintln!(
+                "Warning: the framework is a local dependency, no {} created",
+                LOCK_FILE
+            );
+            return Ok(None);
+        }
+        let url = match &self.spec.framework_git {
+            Some(url) => validate_framework_git(url.clone())?,
+            None => SUI_GIT_URL.to_string(),
+        };
+        let rev = match &self.spec.framework_rev {
+            Some(rev) => validate_framework_rev(rev.clone())?,
+            None => default_framework_rev(),
+        };
+        match resolve_git_rev(&url, &rev) {
+            Ok(commit) => {
+                self.spec.framework_rev = Some(commit);
+                Ok(Some(rev))
+            }
+            Err(e) => {
+                eprintln!(
+                    "Warning: cannot resolve framework revision {:?} ({}), no {} created",
+                    rev, e, LOCK_FILE
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Asks for the main options on `output`, reading the answers from `input`. The
+    /// current values are offered as defaults.
+    fn prompt_options(
+        &mut self,
+        input: &mut impl BufRead,
+        output: &mut impl Write,
+    ) -> anyhow::Result<()> {
+        let name = prompt(
+            input,
+            output,
+            "Package name",
+            self.spec.name.as_deref(),
+            |name| validate_package_name(name).map(|_| ()).map_err(Into::into),
+        )?;
+        self.spec.name = Some(name);
+
+        let templates = template_names();
+        let template = prompt(
+            input,
+            output,
+            &format!("Template ({})", templates.join(", ")),
+            Some(self.spec.template.as_deref().unwrap_or(DEFAULT_TEMPLATE)),
+            |template| Template::find(template).map(|_| ()),
+        )?;
+        self.spec.template = Some(template);
+
+        let edition = prompt(
+            input,
+            output,
+            &format!("Edition ({})", EDITIONS.join(", ")),
+            Some(self.spec.edition.as_deref().unwrap_or(DEFAULT_EDITION)),
+            |edition| validate_edition(edition).map(|_| ()),
         )?;
+        // Keep the manifest free of an `edition` entry when the implied one is chosen.
+        if self.spec.edition.is_some() || edition != DEFAULT_EDITION {
+            self.spec.edition = Some(edition);
+        }
+
+        if self.spec.local_framework.is_none() {
+            let default_rev = default_framework_rev();
+            let rev = prompt(
+                input,
+                output,
+                "Framework revision",
+                Some(self.spec.framework_rev.as_deref().unwrap_or(&default_rev)),
+                |rev| validate_framework_rev(rev.to_string()).map(|_| ()),
+            )?;
+            self.spec.framework_rev = Some(rev);
+        }
         Ok(())
     }
+
+    /// Takes the framework repository and revision from the values of
+    /// `SUI_FRAMEWORK_GIT` and `SUI_FRAMEWORK_REV`, unless overridden on the
+    /// command line.
+    fn apply_env(&mut self, git: Option<String>, rev: Option<String>) -> anyhow::Result<()> {
+        if let Some(git) = git {
+            self.spec.framework_git = Some(
+                validate_framework_git(git)
+                    .with_context(|| format!("Invalid ${}", FRAMEWORK_GIT_ENV))?,
+            );
+        }
+        if let Some(rev) = rev {
+            let rev = validate_framework_rev(rev)
+                .with_context(|| format!("Invalid ${}", FRAMEWORK_REV_ENV))?;
+            if self.spec.framework_rev.is_none() && self.spec.local_framework.is_none() {
+                self.spec.framework_rev = Some(rev);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills in options that were not passed on the command line from `config`.
+    fn apply_config(&mut self, config: NewConfig) {
+        // The framework source is chosen as a whole, so that a `--framework-rev`
+        // flag is not combined with a `local-framework` default.
+        if self.spec.framework_rev.is_none() && self.spec.local_framework.is_none() {
+            self.spec.framework_rev = config.framework_rev;
+            self.spec.local_framework = config.local_framework;
+        }
+        self.spec.template = self.spec.template.take().or(config.template);
+        self.spec.package_version = self.spec.package_version.take().or(config.version);
+        self.spec.edition = self.spec.edition.take().or(config.edition);
+        self.spec.no_gitignore |= config.no_gitignore.unwrap_or(false);
+        self.spec.no_tests |= !self.spec.with_tests && config.no_tests.unwrap_or(false);
+
+        // Named addresses given on the command line override defaults of the same name.
+        let address_name = |assignment: &str| {
+            assignment
+                .split_once('=')
+                .map(|(name, _)| name.trim().to_string())
+        };
+        let overridden: Vec<_> = self
+            .spec
+            .named_addresses
+            .iter()
+            .filter_map(|a| address_name(a.as_str()))
+            .collect();
+        self.spec
+            .named_addresses
+            .extend(config.named_address.into_iter().filter(
+                |a| !matches!(address_name(a.as_str()), Some(name) if overridden.contains(&name)),
+            ));
+    }
+
+    /// Computes the files making up the new package, without touching the filesystem.
+    fn generate(&self, package_root: &Path) -> Result<Scaffold, NewError> {
+        scaffold(&self.spec, package_root)
+    }
+}
+
+impl ScaffoldSpec {
+    fn package_name(&self) -> Result<&str, NewError> {
+        self.name.as_deref().ok_or(NewError::MissingName)
+    }
+}
+
+/// Errors returned by `sui move new` and [`scaffold`].
+#[derive(Debug, Error)]
+pub enum NewError {
+    #[error("Missing the name of the package to create")]
+    MissingName,
+    /// A package or module name that is not a valid Move identifier.
+    #[error("{0}")]
+    InvalidName(String),
+    #[error(
+        "{} already contains {existing}, refusing to overwrite an existing package (pass --overwrite to replace its files)",
+        package_root.display()
+    )]
+    ExistingPackage {
+        package_root: PathBuf,
+        existing: String,
+    },
+    /// The framework dependency can't be resolved, or lacks a requested feature.
+    #[error(transparent)]
+    Framework(anyhow::Error),
+    /// A `--template-git` template can't be fetched or instantiated.
+    #[error(transparent)]
+    Template(anyhow::Error),
+    /// The package files can't be written.
+    #[error(transparent)]
+    Write(anyhow::Error),
+    /// The package was generated but does not build with `--verify`.
+    #[error(transparent)]
+    Verify(anyhow::Error),
+    /// Any other invalid option or combination of options.
+    #[error(transparent)]
+    InvalidOptions(anyhow::Error),
+    /// Some of several packages created in one invocation failed, with their errors.
+    #[error(
+        "Failed to create {} of {total} packages: {}",
+        failures.len(),
+        failures.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", ")
+    )]
+    PackagesFailed {
+        failures: Vec<(String, NewError)>,
+        total: usize,
+    },
+}
+
+impl From<anyhow::Error> for NewError {
+    fn from(error: anyhow::Error) -> Self {
+        // Errors of the helpers are typed where they are raised.
+        error
+            .downcast::<NewError>()
+            .unwrap_or_else(NewError::InvalidOptions)
+    }
+}
+
+/// Computes the files making up the package described by `spec`, to be created
+/// at `package_root`. Nothing is written to the package root, but computing the
+/// files reads:
+/// - the package root's existing `.gitignore`, to extend it, and `move-fmt.toml`,
+///   to keep it;
+/// - the files passed to `--readme-template` and `--manifest-extra`;
+/// - the manifests of `--depends-on` packages, which must exist, and those of a
+///   `--local-framework` and its dependencies, for their named addresses;
+/// - a `--template-git` repository, cloned with git into a temporary directory;
+/// - the home directory, for `--record-command` to record paths under it from `~`.
+pub fn scaffold(spec: &ScaffoldSpec, package_root: &Path) -> Result<Scaffold, NewError> {
+    scaffold_files(spec, package_root).map_err(NewError::from)
+}
+
+fn scaffold_files(spec: &ScaffoldSpec, package_root: &Path) -> anyhow::Result<Scaffold> {
+    let package_name = spec.package_name()?;
+    let flavor = match (&spec.flavor, spec.no_framework) {
+        (Some(name), _) => Flavor::find(name)?,
+        (None, true) => Flavor::find(CORE_FLAVOR)?,
+        (None, false) => Flavor::find(DEFAULT_FLAVOR)?,
+    };
+    let (framework, git_framework) = match (&spec.local_framework, &spec.framework_rev) {
+        (Some(_), _) if flavor.name != DEFAULT_FLAVOR => bail!(
+            "--local-framework can only be used with the {:?} flavor",
+            DEFAULT_FLAVOR
+        ),
+        (Some(local), _) => (
+            local_sui_pkg_path(local).map_err(NewError::Framework)?,
+            None,
+        ),
+        (None, rev) => {
+            let git_framework = GitFramework {
+                url: match &spec.framework_git {
+                    Some(url) => {
+                        validate_framework_git(url.clone()).map_err(NewError::Framework)?
+                    }
+                    None => SUI_GIT_URL.to_string(),
+                },
+                subdir: match &spec.framework_subdir {
+                    Some(subdir) => {
+                        validate_framework_subdir(subdir).map_err(NewError::Framework)?
+                    }
+                    None => flavor.subdir.to_string(),
+                },
+                rev: match rev {
+                    Some(rev) => {
+                        validate_framework_rev(rev.clone()).map_err(NewError::Framework)?
+                    }
+                    None => default_framework_rev(),
+                },
+            };
+            (git_framework.dependency(), Some(git_framework))
+        }
+    };
+    // A git template's manifest names its own framework.
+    let git_framework = git_framework.filter(|_| spec.template_git.is_none());
+    let mut template = Template::find(spec.template.as_deref().unwrap_or(DEFAULT_TEMPLATE))?;
+    if let Some(flavor_template) = flavor.template {
+        if template.name != flavor_template.name {
+            bail!(
+                "Template {:?} uses the Sui framework, only the {:?} template can be used with the {:?} flavor",
+                template.name,
+                flavor_template.name,
+                flavor.name
+            );
+        }
+        if spec.examples {
+            bail!(
+                "--examples uses the Sui framework and cannot be used with the {:?} flavor",
+                flavor.name
+            );
+        }
+        template = flavor_template;
+    }
+    let version = validate_package_version(
+        spec.package_version
+            .as_deref()
+            .unwrap_or(DEFAULT_PACKAGE_VERSION),
+    )?;
+    let license = spec
+        .license
+        .as_deref()
+        .map(License::find)
+        .transpose()?
+        .flatten();
+    let copyright = match &spec.author {
+        Some(author) => {
+            if author.trim().is_empty() || author.contains(|c: char| c.is_control()) {
+                bail!(
+                    "Invalid author {:?}, expected a single line of text",
+                    author
+                );
+            }
+            let year = spec.year.unwrap_or_else(|| year_of(SystemTime::now()));
+            Some(Copyright {
+                year,
+                holder: author.trim(),
+            })
+        }
+        None => None,
+    };
+    let framework_dep_name = match &spec.framework_dep_name {
+        Some(name) => validate_framework_dep_name(name)?,
+        None => flavor.dep_name.to_string(),
+    };
+    // An empty description is left out, like a missing one.
+    let description = spec
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty());
+    let readme = match &spec.readme_template {
+        Some(path) => Some(
+            fs::read_to_string(path)
+                .with_context(|| format!("Cannot read README template {}", path.display()))?,
+        ),
+        None => spec.with_readme.then(|| README.to_string()),
+    };
+    let mut readme_values = BTreeMap::new();
+    readme_values.insert("framework_dep_name", framework_dep_name.clone());
+    match (&spec.local_framework, &git_framework) {
+        (Some(local), _) => {
+            readme_values.insert(
+                "framework",
+                format!("the local framework at {}", local.display()),
+            );
+        }
+        (None, Some(git)) => {
+            readme_values.insert(
+                "framework",
+                format!("revision `{}` of {}", git.rev, git.url),
+            );
+            readme_values.insert("framework_rev", git.rev.clone());
+        }
+        (None, None) => {}
+    }
+    if let Some(description) = description {
+        readme_values.insert("description", description.to_string());
+    }
+    let manifest_extra = spec
+        .manifest_extra
+        .as_deref()
+        .map(read_manifest_extra)
+        .transpose()?;
+    let mut named_addresses = parse_named_addresses(&spec.named_addresses)?;
+    let dev_addresses = parse_named_addresses(&spec.dev_addresses)?;
+    if !spec.allow_reserved_address {
+        let reserved: BTreeSet<String> = match &spec.local_framework {
+            Some(local) => local_framework_address_names(&local.join(SUI_FRAMEWORK_SUBDIR))
+                .map_err(NewError::Framework)?,
+            None => RESERVED_ADDRESS_NAMES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        };
+        if let Some((addr_name, _)) = named_addresses
+            .iter()
+            .chain(&dev_addresses)
+            .find(|(addr_name, _)| reserved.contains(addr_name))
+        {
+            bail!(
+                "Named address {:?} is reserved by the framework: assigning it in the package \
+                 would shadow the framework's own address and break the build. Use another \
+                 name, or pass --allow-reserved-address to assign it anyway",
+                addr_name
+            );
+        }
+        // The package's own address is named after it, or after each member of a
+        // workspace. Invalid names are reported when the package is generated.
+        let own_addresses: Vec<(&str, String)> = if spec.workspace {
+            spec.members
+                .iter()
+                .filter_map(|member| Some((member.as_str(), validate_package_name(member).ok()?)))
+                .collect()
+        } else {
+            let address_name = validate_package_name(package_name).ok().or_else(|| {
+                spec.module_name
+                    .as_deref()
+                    .and_then(|module_name| validate_module_name(module_name).ok())
+            });
+            address_name
+                .map(|address_name| (package_name, address_name))
+                .into_iter()
+                .collect()
+        };
+        if let Some((name, addr_name)) = own_addresses
+            .iter()
+            .find(|(_, addr_name)| reserved.contains(addr_name))
+        {
+            bail!(
+                "Package {:?} would be published at the named address {:?}, which is reserved \
+                 by the framework: it would shadow the framework's own address and break the \
+                 build. Use another name, or pass --allow-reserved-address to create it anyway",
+                name,
+                addr_name
+            );
+        }
+    }
+    for (addr_name, addr_value) in flavor.addresses {
+        if !named_addresses.iter().any(|(name, _)| name == addr_name) {
+            named_addresses.push((addr_name.to_string(), addr_value.to_string()));
+        }
+    }
+    let options = PackageOptions {
+        version: &version,
+        description,
+        edition: spec.edition.as_deref().map(validate_edition).transpose()?,
+        template,
+        with_tests: !spec.no_tests,
+        examples: spec.examples,
+        bare: spec.bare,
+        named_addresses,
+        dev_addresses,
+        header: source_header(copyright.as_ref(), license),
+        readme: readme.as_deref(),
+        readme_values,
+        manifest_extra: manifest_extra.as_ref(),
+    };
+    let mut sui_dependency = vec![(framework_dep_name.clone(), framework)];
+    for (dep_name, dep_path) in parse_local_dependencies(&spec.depends_on, package_root)? {
+        if sui_dependency
+            .iter()
+            .any(|(existing, _)| *existing == dep_name)
+        {
+            bail!("Dependency {:?} is declared more than once", dep_name);
+        }
+        sui_dependency.push((
+            dep_name,
+            format!("{{ local = \"{}\" }}", escape_toml_str(&dep_path)),
+        ));
+    }
+
+    let (mut files, addresses) = if spec.workspace {
+        if spec.members.is_empty() {
+            bail!("A workspace needs at least one member package");
+        }
+        validate_package_name(package_name)?;
+        let mut files = vec![GeneratedFile {
+            path: PathBuf::from(MANIFEST_FILE),
+            contents: Manifest {
+                name: package_name,
+                version: &version,
+                edition: options.edition,
+                description: options.description,
+                dependencies: &sui_dependency,
+                addresses: &[],
+                dev_addresses: &[],
+                extra: options.manifest_extra,
+            }
+            .render()
+            .into_bytes(),
+        }];
+        let mut addresses = Vec::new();
+        // Members get the framework through their dependency on the workspace package.
+        let workspace_dependency = [(package_name.to_string(), "{ local = \"..\" }".to_string())];
+        for member in &spec.members {
+            let ident = validate_package_name(member)?;
+            let (member_files, member_addresses) =
+                options.package_files(member, &ident, &ident, &workspace_dependency)?;
+            files.extend(member_files.into_iter().map(|file| GeneratedFile {
+                path: Path::new(member).join(file.path),
+                contents: file.contents,
+            }));
+            for address in member_addresses {
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+        }
+        (files, addresses)
+    } else {
+        if !spec.members.is_empty() {
+            bail!(
+                "Unexpected member packages {:?} outside of --workspace mode, packages are scaffolded one at a time",
+                spec.members
+            );
+        }
+        let (address_name, module_name) = match &spec.module_name {
+            Some(module_name) => {
+                let module_name = validate_module_name(module_name)?;
+                let address_name =
+                    validate_package_name(package_name).unwrap_or_else(|_| module_name.clone());
+                (address_name, module_name)
+            }
+            None => {
+                let ident = validate_package_name(package_name)?;
+                (ident.clone(), ident)
+            }
+        };
+        match &spec.template_git {
+            Some(url) => {
+                let mut values: BTreeMap<String, String> = [
+                    ("package_name", package_name),
+                    ("module_name", module_name.as_str()),
+                    ("address_name", address_name.as_str()),
+                ]
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+                if let Some(copyright) = &copyright {
+                    values.insert("author".to_string(), copyright.holder.to_string());
+                }
+                values.insert(format!("addresses.{}", address_name), "0x0".to_string());
+                for (addr_name, addr_value) in &options.named_addresses {
+                    values.insert(format!("addresses.{}", addr_name), addr_value.clone());
+                }
+                git_template_files(url, spec.template_rev.as_deref(), &values)
+                    .map_err(NewError::Template)?
+            }
+            None => {
+                options.package_files(package_name, &address_name, &module_name, &sui_dependency)?
+            }
+        }
+    };
+
+    if let Some(license) = license {
+        files.retain(|f| f.path != Path::new(LICENSE_FILE));
+        files.push(GeneratedFile {
+            path: PathBuf::from(LICENSE_FILE),
+            contents: license.render(copyright.as_ref()).into_bytes(),
+        });
+    }
+    if let Some(provider) = spec
+        .ci
+        .as_deref()
+        .map(CiProvider::find)
+        .transpose()?
+        .flatten()
+    {
+        let git_framework = git_framework.as_ref().ok_or_else(|| {
+            anyhow!(
+                "--ci {} cannot be used with --local-framework, CI needs a framework revision to build against",
+                provider.name
+            )
+        })?;
+        files.push(provider.render(git_framework));
+    }
+    // A `.gitignore` provided by a git template is kept as is.
+    if !spec.no_gitignore
+        && !spec.bare
+        && !files.iter().any(|f| f.path == Path::new(GITIGNORE_FILE))
+    {
+        if let Some(contents) = gitignore(package_root)? {
+            files.push(GeneratedFile {
+                path: PathBuf::from(GITIGNORE_FILE),
+                contents: contents.into_bytes(),
+            });
+        }
+    }
+    if spec.with_format_config
+        && !package_root.join(FORMAT_CONFIG_FILE).exists()
+        && !files
+            .iter()
+            .any(|f| f.path == Path::new(FORMAT_CONFIG_FILE))
+    {
+        files.push(GeneratedFile {
+            path: PathBuf::from(FORMAT_CONFIG_FILE),
+            contents: FORMAT_CONFIG.as_bytes().to_vec(),
+        });
+    }
+    if spec.record_command {
+        // The recorded options are the resolved ones, so that they give the same package.
+        let mut resolved = spec.clone();
+        resolved.no_framework = false;
+        resolved.flavor = Some(flavor.name.to_string());
+        let home = dirs::home_dir();
+        if let Some(git) = &git_framework {
+            resolved.framework_git =
+                (git.url != SUI_GIT_URL).then(|| record_url(&git.url, home.as_deref()));
+            resolved.framework_subdir = Some(git.subdir.clone());
+            resolved.framework_rev = Some(git.rev.clone());
+        }
+        resolved.template_git = spec
+            .template_git
+            .as_deref()
+            .map(|url| record_url(url, home.as_deref()));
+        if spec.template_git.is_none() {
+            resolved.template = Some(template.name.to_string());
+        }
+        resolved.package_version = Some(version.clone());
+        resolved.framework_dep_name = Some(framework_dep_name.clone());
+        resolved.year = copyright.as_ref().map(|c| c.year);
+        for path in [
+            &mut resolved.local_framework,
+            &mut resolved.readme_template,
+            &mut resolved.manifest_extra,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = record_path(path, home.as_deref());
+        }
+        for dependency in &mut resolved.depends_on {
+            if let Some((name, path)) = dependency.split_once('=') {
+                let path = record_path(Path::new(path), home.as_deref());
+                *dependency = format!("{}={}", name, path.display());
+            }
+        }
+        let mut options = match serde_json::to_value(&resolved)? {
+            serde_json::Value::Object(options) => options,
+            _ => unreachable!("options are serialized as a map"),
+        };
+        options.retain(|_, value| {
+            !matches!(
+                value,
+                serde_json::Value::Null | serde_json::Value::Bool(false)
+            ) && value.as_array().map_or(true, |values| !values.is_empty())
+        });
+        let record = ScaffoldRecord {
+            sui_version: env!("CARGO_PKG_VERSION"),
+            command: recorded_command(&options),
+            options,
+            addresses: addresses
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect(),
+        };
+        files.push(GeneratedFile {
+            path: PathBuf::from(SCAFFOLD_RECORD_FILE),
+            contents: format!("{}\n", serde_json::to_string_pretty(&record)?).into_bytes(),
+        });
+    }
+    Ok(Scaffold {
+        files,
+        framework_dep_name,
+        framework: git_framework,
+        addresses,
+    })
+}
+
+/// Options shared by every package generated by an invocation of `sui move new`.
+struct PackageOptions<'a> {
+    version: &'a str,
+    description: Option<&'a str>,
+    edition: Option<&'a str>,
+    template: &'static Template,
+    with_tests: bool,
+    /// Whether to add the `EXAMPLES` modules.
+    examples: bool,
+    /// Whether to only render the manifest.
+    bare: bool,
+    /// Named addresses requested on the command line, in addition to the package's own.
+    named_addresses: Vec<(String, String)>,
+    /// Entries of the `[dev-addresses]` section.
+    dev_addresses: Vec<(String, String)>,
+    /// Comment lines prepended to every generated Move source.
+    header: String,
+    /// Template of the `README.md` to generate, if any.
+    readme: Option<&'a str>,
+    /// Sections to merge into every generated manifest.
+    manifest_extra: Option<&'a toml::value::Table>,
+    /// Values of the README placeholders that are the same for every package.
+    readme_values: BTreeMap<&'static str, String>,
+}
+
+impl PackageOptions<'_> {
+    /// Renders the files of package `name`, whose named address is `address_name`
+    /// and starter module `module_name`. Returns them along with the package's
+    /// named addresses.
+    fn package_files(
+        &self,
+        name: &str,
+        address_name: &str,
+        module_name: &str,
+        dependencies: &[(String, String)],
+    ) -> anyhow::Result<(Vec<GeneratedFile>, Vec<(String, String)>)> {
+        if self.examples && EXAMPLES.iter().any(|example| example.name == module_name) {
+            bail!(
+                "Module name {:?} is taken by an example module, pass --module-name to choose another one",
+                module_name
+            );
+        }
+        let mut addresses = self.named_addresses.clone();
+        if !addresses
+            .iter()
+            .any(|(addr_name, _)| addr_name == address_name)
+        {
+            addresses.insert(0, (address_name.to_string(), "0x0".to_string()));
+        }
+        let mut files = vec![GeneratedFile {
+            path: PathBuf::from(MANIFEST_FILE),
+            contents: Manifest {
+                name,
+                version: self.version,
+                edition: self.edition,
+                description: self.description,
+                dependencies,
+                addresses: &addresses,
+                dev_addresses: &self.dev_addresses,
+                extra: self.manifest_extra,
+            }
+            .render()
+            .into_bytes(),
+        }];
+        if self.bare {
+            return Ok((files, addresses));
+        }
+        let mut sources = self
+            .template
+            .render_files(address_name, module_name, self.with_tests);
+        if self.examples {
+            for example in EXAMPLES {
+                sources.extend(example.render_example_files(address_name, self.with_tests));
+            }
+        }
+        files.extend(sources.into_iter().map(|file| GeneratedFile {
+            contents: [self.header.as_bytes(), &file.contents].concat(),
+            ..file
+        }));
+        let readme = match self.readme {
+            Some(template) => {
+                let mut values: BTreeMap<String, String> = self
+                    .readme_values
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.clone()))
+                    .collect();
+                for (placeholder, value) in [
+                    ("package_name", name),
+                    ("module_name", module_name),
+                    ("address_name", address_name),
+                ] {
+                    values.insert(placeholder.to_string(), value.to_string());
+                }
+                let mut missing = BTreeSet::new();
+                let readme = substitute_placeholders(template, &values, &mut missing);
+                if !missing.is_empty() {
+                    let missing: Vec<_> = missing
+                        .iter()
+                        .map(|name| format!("{{{{{}}}}}", name))
+                        .collect();
+                    bail!(
+                        "The README template uses placeholders without a value: {}",
+                        missing.join(", ")
+                    );
+                }
+                Some(readme)
+            }
+            None => None,
+        };
+        if let Some(readme) = readme {
+            files.push(GeneratedFile {
+                path: PathBuf::from(README_FILE),
+                contents: readme.into_bytes(),
+            });
+        }
+        Ok((files, addresses))
+    }
+}
+
+/// The defaults `sui move new` takes from its environment rather than from the
+/// command line.
+#[derive(Default)]
+struct Environment {
+    /// Values of `SUI_FRAMEWORK_GIT` and `SUI_FRAMEWORK_REV`, if set and not empty.
+    framework_git: Option<String>,
+    framework_rev: Option<String>,
+    /// The home directory, holding the default config file.
+    home: Option<PathBuf>,
+}
+
+impl Environment {
+    /// Reads the environment of this process.
+    fn current() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Self {
+            framework_git: var(FRAMEWORK_GIT_ENV),
+            framework_rev: var(FRAMEWORK_REV_ENV),
+            home: dirs::home_dir(),
+        }
+    }
+}
+
+/// Defaults for `sui move new`, read from `~/.sui/move-new.toml`. Keys mirror
+/// the command line options, which take precedence over them.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct NewConfig {
+    framework_rev: Option<String>,
+    local_framework: Option<PathBuf>,
+    template: Option<String>,
+    version: Option<String>,
+    edition: Option<String>,
+    #[serde(default)]
+    named_address: Vec<String>,
+    no_tests: Option<bool>,
+    no_gitignore: Option<bool>,
+}
+
+impl NewConfig {
+    /// Reads the config at `path`, or at the default location under `home` if `path`
+    /// is not provided, in which case a missing file yields the empty config.
+    fn load(path: Option<&Path>, home: Option<&Path>) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match home {
+                Some(home) if home.join(CONFIG_FILE).exists() => home.join(CONFIG_FILE),
+                _ => return Ok(Self::default()),
+            },
+        };
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Cannot read config file {}", path.display()))?;
+        let config: Self = toml::from_str(&contents)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        if config.framework_rev.is_some() && config.local_framework.is_some() {
+            bail!(
+                "Invalid config file {}: framework-rev and local-framework cannot both be set",
+                path.display()
+            );
+        }
+        Ok(config)
+    }
+}
+
+/// The in-memory description of a package generated by `sui move new`.
+pub struct Scaffold {
+    pub files: Vec<GeneratedFile>,
+    /// Key of the framework in the `[dependencies]` section of the manifest.
+    pub framework_dep_name: String,
+    /// The Sui framework the package depends on, or the standard library of the
+    /// `core` flavor. `None` for a local framework, and for a `--template-git`
+    /// template, whose manifest names its own.
+    pub framework: Option<GitFramework>,
+    /// Contents of the `[addresses]` section of the manifest.
+    pub addresses: Vec<(String, String)>,
+}
+
+impl Scaffold {
+    /// SHA3-256 of the generated files, as a hex string. Paths use `/` separators
+    /// and text files `\n` line endings, so that the hash is the same on every
+    /// platform.
+    pub fn sha(&self) -> String {
+        let mut files: Vec<(String, Vec<u8>)> = self
+            .files
+            .iter()
+            .map(|file| {
+                let path: Vec<_> = file
+                    .path
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect();
+                let contents = match file.text() {
+                    Some(text) => text.replace("\r\n", "\n").into_bytes(),
+                    None => file.contents.clone(),
+                };
+                (path.join("/"), contents)
+            })
+            .collect();
+        files.sort();
+        let mut hasher = Sha3_256::new();
+        for (path, contents) in files {
+            // Length prefixes keep the boundaries between paths and contents unambiguous.
+            hasher.update((path.len() as u64).to_le_bytes());
+            hasher.update(path.as_bytes());
+            hasher.update((contents.len() as u64).to_le_bytes());
+            hasher.update(&contents);
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// A revision of the Sui framework in a git repository.
+pub struct GitFramework {
+    pub url: String,
+    /// Directory of the framework package within the repository.
+    pub subdir: String,
+    pub rev: String,
+}
+
+impl GitFramework {
+    /// Renders the manifest's dependency on the framework.
+    fn dependency(&self) -> String {
+        format!(
+            "{{ git = \"{}\", subdir = \"{}\", rev = \"{}\" }}",
+            escape_toml_str(&self.url),
+            escape_toml_str(&self.subdir),
+            self.rev
+        )
+    }
+}
+
+/// Output of `sui move new --json`.
+#[derive(Serialize)]
+struct Summary<'a> {
+    package_root: PathBuf,
+    files: Vec<&'a Path>,
+    /// Existing files left untouched by `--sync`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    skipped: Vec<&'a Path>,
+    framework_rev: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manifest_sha: Option<&'a str>,
+    named_addresses: BTreeMap<&'a str, &'a str>,
+}
+
+/// Contents of the `.sui-scaffold.json` written by `--record-command`.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ScaffoldRecord<'a> {
+    sui_version: &'a str,
+    /// A `sui move new` invocation generating the same package.
+    command: String,
+    /// The resolved options, keyed by flag name.
+    options: serde_json::Map<String, serde_json::Value>,
+    addresses: BTreeMap<&'a str, &'a str>,
+}
+
+/// Renders the `sui move new` invocation passing the recorded `options`.
+fn recorded_command(options: &serde_json::Map<String, serde_json::Value>) -> String {
+    let quote = |value: &serde_json::Value| match value {
+        serde_json::Value::String(s) => quote_word(s),
+        other => other.to_string(),
+    };
+    let mut words = Vec::new();
+    // The framework repository is only taken from the environment.
+    if let Some(git) = options.get("framework-git") {
+        words.push(format!("{}={}", FRAMEWORK_GIT_ENV, quote(git)));
+    }
+    words.extend(["sui", "move", "new"].map(String::from));
+    for positional in ["name", "members"] {
+        match options.get(positional) {
+            Some(serde_json::Value::Array(values)) => words.extend(values.iter().map(quote)),
+            Some(value) => words.push(quote(value)),
+            None => {}
+        }
+    }
+    for (flag, value) in options {
+        if matches!(flag.as_str(), "name" | "members" | "framework-git") {
+            continue;
+        }
+        match value {
+            serde_json::Value::Bool(_) => words.push(format!("--{}", flag)),
+            serde_json::Value::Array(values) => {
+                for value in values {
+                    words.push(format!("--{}", flag));
+                    words.push(quote(value));
+                }
+            }
+            value => {
+                words.push(format!("--{}", flag));
+                words.push(quote(value));
+            }
+        }
+    }
+    words.join(" ")
+}
+
+/// Quotes `word` for a shell. A path under `~`, possibly after `NAME=`, is written
+/// relative to `"$HOME"` instead, so that it still expands when the command is run.
+fn quote_word(word: &str) -> String {
+    let (name, path) = match word.split_once('=') {
+        Some((name, path)) if path.starts_with('~') => (Some(name), path),
+        _ => (None, word),
+    };
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => return shell_words::quote(word).into_owned(),
+    };
+    let mut quoted = name.map_or_else(String::new, |name| format!("{}=", shell_words::quote(name)));
+    quoted.push_str("\"$HOME\"");
+    if !rest.is_empty() {
+        quoted.push_str(&shell_words::quote(rest));
+    }
+    quoted
+}
+
+/// Makes the git `url` fit for the record of a package: credentials are removed,
+/// and a local repository is recorded as its path, like by `record_path`.
+fn record_url(url: &str, home: Option<&Path>) -> String {
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    if path.contains("://") || !Path::new(path).is_absolute() {
+        return redact_url(url);
+    }
+    record_path(Path::new(path), home).display().to_string()
+}
+
+/// Makes `path` fit for the record of a package, abbreviating the `home` directory
+/// to `~` so that the record does not reveal it. Relative paths are kept as passed.
+fn record_path(path: &Path, home: Option<&Path>) -> PathBuf {
+    match home.and_then(|home| {
+        normalize_path(path)
+            .strip_prefix(home)
+            .ok()
+            .map(Path::to_path_buf)
+    }) {
+        Some(relative) if path.is_absolute() => Path::new("~").join(relative),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Removes any credentials from the authority of `url`.
+fn redact_url(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let authority_end = rest.find('/').unwrap_or(rest.len());
+            match rest[..authority_end].rfind('@') {
+                Some(at) => format!("{}://{}", scheme, &rest[at + 1..]),
+                None => url.to_string(),
+            }
+        }
+        None => url.to_string(),
+    }
+}
+
+/// Lists the `created` files of the package at `package_root` on `output`, one per
+/// line, followed by the `skipped` ones that already existed.
+fn print_created(
+    output: &mut impl Write,
+    package_root: &Path,
+    created: &[&Path],
+    skipped: &[&Path],
+) -> anyhow::Result<()> {
+    for path in created {
+        writeln!(output, "Created {}", package_root.join(path).display())?;
+    }
+    for path in skipped {
+        writeln!(
+            output,
+            "Skipped {} (already exists)",
+            package_root.join(path).display()
+        )?;
+    }
+    Ok(())
+}
+
+/// Renders a `Move.lock` recording that framework dependency `dep_name`, pinned to
+/// the commit of `framework`, was resolved from revision `rev`.
+fn lock_file(dep_name: &str, framework: &GitFramework, rev: &str) -> String {
+    format!(
+        "# @generated by `sui move new --lock`, records the revision each git dependency\n\
+         # of Move.toml was pinned to a commit from.\n\
+         \n\
+         [[dependency]]\n\
+         name = \"{}\"\n\
+         git = \"{}\"\n\
+         subdir = \"{}\"\n\
+         rev = \"{}\"\n\
+         commit = \"{}\"\n",
+        dep_name,
+        escape_toml_str(&framework.url),
+        escape_toml_str(&framework.subdir),
+        rev,
+        framework.rev
+    )
+}
+
+fn is_commit_hash(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Resolves `rev` (a branch, tag or commit) of the repository at `url` to a commit hash.
+fn resolve_git_rev(url: &str, rev: &str) -> anyhow::Result<String> {
+    if is_commit_hash(rev) {
+        return Ok(rev.to_ascii_lowercase());
+    }
+    // `--` keeps a revision such as `--upload-pack=...` from being taken for an option.
+    let output = Command::new("git")
+        .args(["ls-remote", "--", url, rev])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("cannot run git")?;
+    if !output.status.success() {
+        bail!(
+            "git ls-remote failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let refs = String::from_utf8(output.stdout)?;
+    // Annotated tags are listed twice, the `^{}` entry is the commit they point to.
+    let mut refs: Vec<(&str, &str)> = refs
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect();
+    refs.sort_by_key(|(_, name)| !name.ends_with("^{}"));
+    match refs.first() {
+        Some((commit, _)) if is_commit_hash(commit) => Ok(commit.to_string()),
+        _ => bail!("no branch or tag named {:?} in {}", rev, url),
+    }
+}
+
+/// Clones the template repository at `url`, checked out at `rev` if given, and
+/// instantiates its files with the placeholder `values`. Returns them along with
+/// the named addresses declared by the template's manifest. Nothing is written to
+/// the package root.
+fn git_template_files(
+    url: &str,
+    rev: Option<&str>,
+    values: &BTreeMap<String, String>,
+) -> anyhow::Result<(Vec<GeneratedFile>, Vec<(String, String)>)> {
+    if url.starts_with('-') || matches!(rev, Some(rev) if rev.starts_with('-')) {
+        bail!("Invalid template repository {:?}", url);
+    }
+    // The clone is removed when `checkout` is dropped, whether scaffolding succeeds or not.
+    let checkout = tempfile::tempdir()?;
+    git(&["clone", "--quiet", "--", url, "."], checkout.path())
+        .with_context(|| format!("Cannot clone template {}", url))?;
+    if let Some(rev) = rev {
+        git(&["checkout", "--quiet", "--detach", rev], checkout.path())
+            .with_context(|| format!("Cannot check out revision {:?} of template {}", rev, url))?;
+    }
+
+    let mut files = Vec::new();
+    read_template_dir(checkout.path(), checkout.path(), &mut files)?;
+    let mut missing = BTreeSet::new();
+    for file in &mut files {
+        if let Some(path) = file.path.to_str() {
+            file.path = PathBuf::from(substitute_placeholders(path, values, &mut missing));
+        }
+        // Binary files are copied as they are.
+        if let Some(text) = file.text() {
+            let contents = substitute_placeholders(text, values, &mut missing);
+            file.contents = contents.into_bytes();
+        }
+    }
+    if !missing.is_empty() {
+        let missing: Vec<_> = missing
+            .iter()
+            .map(|name| format!("{{{{{}}}}}", name))
+            .collect();
+        bail!(
+            "Template {} uses placeholders without a value: {} (pass --author, or \
+             --named-address NAME=VALUE for addresses.NAME)",
+            url,
+            missing.join(", ")
+        );
+    }
+
+    let manifest = files
+        .iter()
+        .find(|f| f.path == Path::new(MANIFEST_FILE))
+        .ok_or_else(|| {
+            anyhow!(
+                "Template {} has no {}, it is not a Move package",
+                url,
+                MANIFEST_FILE
+            )
+        })?;
+    let manifest: toml::Value = toml::from_str(manifest.text().unwrap_or_default())
+        .with_context(|| format!("Template {} has an invalid {}", url, MANIFEST_FILE))?;
+    let addresses = match manifest.get("addresses").and_then(|a| a.as_table()) {
+        Some(addresses) => addresses
+            .iter()
+            .map(|(name, value)| (name.clone(), value.as_str().unwrap_or_default().to_string()))
+            .collect(),
+        None => Vec::new(),
+    };
+    Ok((files, addresses))
+}
+
+/// Replaces each `{{name}}` placeholder in `text` by its value in `values`, in a
+/// single pass so that substituted values are never expanded themselves. Names
+/// are made of alphanumerics, `_` and `.`; other text between double braces is
+/// left as is, as are `${{ ... }}` expressions such as those of GitHub Actions
+/// workflows. Names without a value are added to `missing`.
+fn substitute_placeholders(
+    text: &str,
+    values: &BTreeMap<String, String>,
+    missing: &mut BTreeSet<String>,
+) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let offset = text.len() - rest.len() + start;
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) if !text[..offset].ends_with('$') && is_placeholder_name(&after[..end]) => {
+                let name = &after[..end];
+                match values.get(name) {
+                    Some(value) => result.push_str(value),
+                    None => {
+                        missing.insert(name.to_string());
+                        result.push_str(&rest[start..start + end + 4]);
+                    }
+                }
+                rest = &after[end + 2..];
+            }
+            _ => {
+                result.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn is_placeholder_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Reads the files under `dir` into `files`, with paths relative to `root`,
+/// skipping git metadata. Symbolic links are rejected: following them would copy
+/// files from outside the template, such as the user's keys, into the package.
+fn read_template_dir(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<GeneratedFile>,
+) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let file_type = fs::symlink_metadata(&path)?.file_type();
+        let relative = path.strip_prefix(root)?.to_path_buf();
+        if file_type.is_dir() {
+            if entry.file_name() != ".git" {
+                read_template_dir(root, &path, files)?;
+            }
+        } else if file_type.is_file() {
+            files.push(GeneratedFile {
+                path: relative,
+                contents: fs::read(&path)?,
+            });
+        } else {
+            bail!(
+                "{} is a symbolic link or special file, templates can only contain regular files",
+                relative.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Runs git with `args` in `dir`, failing with git's error output.
+fn git(args: &[&str], dir: &Path) -> anyhow::Result<()> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("cannot run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Fails if `package_root` already holds a package, unless `overwrite` is set.
+fn check_no_existing_package(
+    package_root: &Path,
+    overwrite: bool,
+    bare: bool,
+) -> Result<(), NewError> {
+    if overwrite {
+        return Ok(());
+    }
+    // A bare package is meant to be created next to existing sources.
+    let existing_files: &[&str] = if bare {
+        &[MANIFEST_FILE]
+    } else {
+        &[MANIFEST_FILE, SOURCES_DIR]
+    };
+    for existing in existing_files {
+        if package_root.join(existing).exists() {
+            return Err(NewError::ExistingPackage {
+                package_root: package_root.to_path_buf(),
+                existing: existing.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// A file generated by `sui move new`, relative to the package root.
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+impl GeneratedFile {
+    /// The contents of the file, unless it is binary.
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.contents).ok()
+    }
+}
+
+/// Writes `files` under `package_root`, creating directories as needed.
+fn write_files(package_root: &Path, files: &[GeneratedFile], bare: bool) -> anyhow::Result<()> {
+    create_package_root(package_root)?;
+    // A package without modules, such as a workspace root, still needs `sources/`
+    // to build. A bare package only gets its manifest.
+    if !bare {
+        fs::create_dir_all(package_root.join(SOURCES_DIR))?;
+    }
+    for file in files {
+        let path = package_root.join(&file.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, &file.contents)?;
+    }
+    Ok(())
+}
+
+/// Creates `package_root` along with its missing parents, explaining which
+/// ancestor is at fault if that fails.
+fn create_package_root(package_root: &Path) -> anyhow::Result<()> {
+    let err = match fs::create_dir_all(package_root) {
+        Ok(()) => return Ok(()),
+        Err(err) => err,
+    };
+    let existing = package_root
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.exists())
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => bail!(
+            "Cannot create {}: permission denied to create directories in {}",
+            package_root.display(),
+            existing.display()
+        ),
+        _ if !existing.is_dir() => bail!(
+            "Cannot create {}: parent {} is not a directory",
+            package_root.display(),
+            existing.display()
+        ),
+        _ => bail!(
+            "Cannot create {}: parent directories are missing and cannot be created: {}",
+            package_root.display(),
+            err
+        ),
+    }
+}
+
+/// Contents of the generated `Move.toml`.
+struct Manifest<'a> {
+    name: &'a str,
+    version: &'a str,
+    edition: Option<&'a str>,
+    description: Option<&'a str>,
+    dependencies: &'a [(String, String)],
+    addresses: &'a [(String, String)],
+    dev_addresses: &'a [(String, String)],
+    /// Sections to merge into the manifest, see `--manifest-extra`.
+    extra: Option<&'a toml::value::Table>,
+}
+
+impl Manifest<'_> {
+    /// Renders the manifest, in the same layout as `move new`, with the entries of
+    /// `extra` merged in.
+    fn render(&self) -> String {
+        let quoted = |value: &str| format!("\"{}\"", escape_toml_str(value));
+        let mut package = vec![
+            ("name".to_string(), quoted(self.name)),
+            ("version".to_string(), format!("\"{}\"", self.version)),
+        ];
+        if let Some(edition) = self.edition {
+            package.push(("edition".to_string(), format!("\"{}\"", edition)));
+        }
+        if let Some(description) = self.description {
+            package.push(("description".to_string(), quoted(description)));
+        }
+        let addresses = |addresses: &[(String, String)]| {
+            addresses
+                .iter()
+                .map(|(addr_name, addr_value)| (addr_name.clone(), format!("\"{}\"", addr_value)))
+                .collect::<Vec<_>>()
+        };
+        let mut sections = vec![
+            ("package".to_string(), package),
+            ("dependencies".to_string(), self.dependencies.to_vec()),
+            ("addresses".to_string(), addresses(self.addresses)),
+        ];
+        if !self.dev_addresses.is_empty() {
+            sections.push(("dev-addresses".to_string(), addresses(self.dev_addresses)));
+        }
+
+        // Entries of the fragment are added to their section, replacing any of the same key.
+        for (section_name, section) in self.extra.into_iter().flatten() {
+            let index = match sections.iter().position(|(name, _)| name == section_name) {
+                Some(index) => index,
+                None => {
+                    sections.push((section_name.clone(), Vec::new()));
+                    sections.len() - 1
+                }
+            };
+            let entries = &mut sections[index].1;
+            for (key, value) in section.as_table().into_iter().flatten() {
+                let value = render_toml_value(value);
+                match entries.iter_mut().find(|(existing, _)| existing == key) {
+                    Some(entry) => entry.1 = value,
+                    None => entries.push((key.clone(), value)),
+                }
+            }
+        }
+
+        let mut manifest = String::new();
+        for (section_name, entries) in sections {
+            if !manifest.is_empty() {
+                manifest.push('\n');
+            }
+            manifest.push_str(&format!("[{}]\n", render_toml_key(&section_name)));
+            for (key, value) in entries {
+                manifest.push_str(&format!("{} = {}\n", render_toml_key(&key), value));
+            }
+        }
+        manifest
+    }
+}
+
+/// Renders `key` bare if possible, or else quoted.
+fn render_toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        format!("\"{}\"", escape_toml_str(key))
+    }
+}
+
+/// Renders `value` on a single line, with tables inlined.
+fn render_toml_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => format!("\"{}\"", escape_toml_str(s)),
+        toml::Value::Array(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .map(render_toml_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        toml::Value::Table(table) => format!(
+            "{{ {} }}",
+            table
+                .iter()
+                .map(|(key, value)| format!(
+                    "{} = {}",
+                    render_toml_key(key),
+                    render_toml_value(value)
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // Numbers, booleans and dates are rendered the same inline.
+        other => other.to_string(),
+    }
+}
+
+/// Reads the TOML fragment at `path` to merge into the generated manifests,
+/// made of sections only.
+fn read_manifest_extra(path: &Path) -> anyhow::Result<toml::value::Table> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Cannot read manifest fragment {}", path.display()))?;
+    let fragment: toml::value::Table = toml::from_str(&contents)
+        .with_context(|| format!("Invalid manifest fragment {}", path.display()))?;
+    if let Some((key, _)) = fragment.iter().find(|(_, value)| !value.is_table()) {
+        bail!(
+            "Invalid manifest fragment {}: {:?} is not a section, only sections such as \
+             [dependencies] can be merged into the manifest",
+            path.display(),
+            key
+        );
+    }
+    Ok(fragment)
+}
+
+/// Entries every package's `.gitignore` should contain.
+const GITIGNORE_ENTRIES: &[&str] = &["build/"];
+const GITIGNORE: &str = "build/
+
+# Move.lock records the exact dependency revisions the package was built with.
+# Commit it for reproducible builds, or uncomment the line below to ignore it.
+# Move.lock
+";
+
+/// Computes the `.gitignore` for `package_root`: either a fresh one, or any
+/// existing one with missing entries appended. Returns `None` if an existing
+/// `.gitignore` needs no changes.
+fn gitignore(package_root: &Path) -> anyhow::Result<Option<String>> {
+    let path = package_root.join(GITIGNORE_FILE);
+    if !path.exists() {
+        return Ok(Some(GITIGNORE.to_string()));
+    }
+
+    let mut contents = fs::read_to_string(&path)?;
+    let missing: Vec<_> = GITIGNORE_ENTRIES
+        .iter()
+        .filter(|entry| !contents.lines().any(|line| line.trim() == **entry))
+        .collect();
+    if missing.is_empty() {
+        return Ok(None);
+    }
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for entry in missing {
+        contents.push_str(entry);
+        contents.push('\n');
+    }
+    Ok(Some(contents))
+}
+
+/// README written by `--with-readme`, unless `--readme-template` is passed.
+const README: &str = include_str!("templates/readme.md.tmpl");
+
+/// Default settings written by `--with-format-config`.
+const FORMAT_CONFIG: &str = "# Formatting settings shared by everyone working on this package.
+max_width = 100
+indent_size = 4
+hard_tabs = false
+";
+
+/// A starter module, with its unit tests, that `sui move new --template` can scaffold.
+#[derive(Serialize)]
+pub struct Template {
+    pub name: &'static str,
+    pub description: &'static str,
+    #[serde(skip)]
+    source: &'static str,
+    #[serde(skip)]
+    tests: &'static str,
+}
+
+pub const DEFAULT_TEMPLATE: &str = "basic";
+
+pub(crate) const TEMPLATES: &[Template] = &[
+    Template {
+        name: "basic",
+        description: "An empty module",
+        source: include_str!("templates/basic.move.tmpl"),
+        tests: include_str!("templates/basic_tests.move.tmpl"),
+    },
+    Template {
+        name: "coin",
+        description: "A fungible token with a mintable supply",
+        source: include_str!("templates/coin.move.tmpl"),
+        tests: include_str!("templates/coin_tests.move.tmpl"),
+    },
+    Template {
+        name: "nft",
+        description: "A minimal NFT that can be minted, transferred and burned",
+        source: include_str!("templates/nft.move.tmpl"),
+        tests: include_str!("templates/nft_tests.move.tmpl"),
+    },
+    Template {
+        name: "counter",
+        description: "A shared counter object",
+        source: include_str!("templates/counter.move.tmpl"),
+        tests: include_str!("templates/counter_tests.move.tmpl"),
+    },
+];
+
+/// The `basic` template, with tests that only use the standard library.
+const STDLIB_TEMPLATE: Template = Template {
+    name: DEFAULT_TEMPLATE,
+    description: "An empty module depending on the Move standard library only",
+    source: include_str!("templates/basic.move.tmpl"),
+    tests: include_str!("templates/basic_stdlib_tests.move.tmpl"),
+};
+
+/// A Move environment that `sui move new --flavor` can target.
+pub struct Flavor {
+    pub name: &'static str,
+    /// Key of the framework in the `[dependencies]` section of the manifest.
+    dep_name: &'static str,
+    /// Directory of the framework package within the Sui repository.
+    subdir: &'static str,
+    /// Named addresses added to the manifest unless assigned on the command line.
+    addresses: &'static [(&'static str, &'static str)],
+    /// The only template that can be used with the flavor, replacing the one of the
+    /// same name, or `None` if all `TEMPLATES` can.
+    template: Option<&'static Template>,
+}
+
+const DEFAULT_FLAVOR: &str = "sui";
+/// The flavor selected by `--no-framework`.
+const CORE_FLAVOR: &str = "core";
+
+pub(crate) const FLAVORS: &[Flavor] = &[
+    Flavor {
+        name: DEFAULT_FLAVOR,
+        dep_name: SUI_PKG_NAME,
+        subdir: SUI_FRAMEWORK_SUBDIR,
+        addresses: &[],
+        template: None,
+    },
+    Flavor {
+        name: CORE_FLAVOR,
+        dep_name: STDLIB_PKG_NAME,
+        subdir: STDLIB_SUBDIR,
+        addresses: &[],
+        template: Some(&STDLIB_TEMPLATE),
+    },
+];
+
+/// Names accepted by `--flavor`, also offered as its completions.
+pub(crate) fn flavor_names() -> Vec<&'static str> {
+    FLAVORS.iter().map(|f| f.name).collect()
+}
+
+impl Flavor {
+    pub(crate) fn find(name: &str) -> anyhow::Result<&'static Flavor> {
+        FLAVORS.iter().find(|f| f.name == name).ok_or_else(|| {
+            anyhow!(
+                "Unknown flavor {:?}, available flavors are: {}",
+                name,
+                flavor_names().join(", ")
+            )
+        })
+    }
+}
+
+/// Names accepted by `--template`, also offered as its completions.
+pub(crate) fn template_names() -> Vec<&'static str> {
+    TEMPLATES.iter().map(|t| t.name).collect()
+}
+
+/// Prints the name and description of each of the `TEMPLATES` to `output`.
+fn list_templates(output: &mut impl Write, json: bool) -> anyhow::Result<()> {
+    if json {
+        writeln!(output, "{}", serde_json::to_string_pretty(TEMPLATES)?)?;
+        return Ok(());
+    }
+    let width = TEMPLATES.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    for template in TEMPLATES {
+        writeln!(
+            output,
+            "{:width$}  {}",
+            template.name,
+            template.description,
+            width = width
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes a bash completion script for `sui move new` to `output`, offering the
+/// possible values of each option, such as the names of the `TEMPLATES`. Other
+/// `sui` commands are left to the completion of `sui` registered before the
+/// script is sourced, if any, and otherwise complete file names.
+fn bash_completions(output: &mut impl Write) -> anyhow::Result<()> {
+    let command = <New as clap::CommandFactory>::command();
+    let mut flags = vec!["--help".to_string()];
+    let mut values = Vec::new();
+    for arg in command.get_arguments().filter(|arg| !arg.is_hide_set()) {
+        let long = match arg.get_long() {
+            Some(long) => format!("--{}", long),
+            None => continue,
+        };
+        if let Some(possible_values) = arg.get_value_parser().possible_values() {
+            let names: Vec<_> = possible_values
+                .filter(|value| !value.is_hide_set())
+                .map(|value| value.get_name().to_string())
+                .collect();
+            values.push((long.clone(), names.join(" ")));
+        }
+        flags.push(long);
+    }
+
+    // The completion function of `sui` that was there before, kept for its other
+    // commands. Sourcing the script again keeps the one found the first time.
+    writeln!(
+        output,
+        "if [[ \"$(complete -p sui 2>/dev/null)\" =~ -F\\ ([^ ]+) && \"${{BASH_REMATCH[1]}}\" != _sui_move_new ]]; then"
+    )?;
+    writeln!(
+        output,
+        "    _sui_move_new_fallback=\"${{BASH_REMATCH[1]}}\""
+    )?;
+    writeln!(output, "fi")?;
+    writeln!(output, "_sui_move_new() {{")?;
+    writeln!(output, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+    writeln!(output, "    local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"")?;
+    // `move` is the first command word and `new` one of the next, wherever the
+    // options between them are.
+    writeln!(output, "    local word words=()")?;
+    writeln!(
+        output,
+        "    for word in \"${{COMP_WORDS[@]:1:COMP_CWORD-1}}\"; do"
+    )?;
+    writeln!(
+        output,
+        "        [[ \"$word\" == -* ]] || words+=(\"$word\")"
+    )?;
+    writeln!(output, "    done")?;
+    writeln!(
+        output,
+        "    if [[ \"${{words[0]}}\" != move || \" ${{words[*]:1}} \" != *\" new \"* ]]; then"
+    )?;
+    writeln!(
+        output,
+        "        if [[ -n \"$_sui_move_new_fallback\" ]]; then"
+    )?;
+    writeln!(output, "            \"$_sui_move_new_fallback\" \"$@\"")?;
+    writeln!(output, "        else")?;
+    writeln!(output, "            compopt -o default")?;
+    writeln!(output, "            COMPREPLY=()")?;
+    writeln!(output, "        fi")?;
+    writeln!(output, "        return")?;
+    writeln!(output, "    fi")?;
+    writeln!(output, "    case \"$prev\" in")?;
+    for (flag, names) in &values {
+        writeln!(output, "        {})", flag)?;
+        writeln!(
+            output,
+            "            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+            names
+        )?;
+        writeln!(output, "            return 0")?;
+        writeln!(output, "            ;;")?;
+    }
+    writeln!(output, "    esac")?;
+    writeln!(output, "    if [[ \"$cur\" == -* ]]; then")?;
+    writeln!(
+        output,
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+        flags.join(" ")
+    )?;
+    writeln!(output, "        return 0")?;
+    writeln!(output, "    fi")?;
+    writeln!(output, "    compopt -o default")?;
+    writeln!(output, "    COMPREPLY=()")?;
+    writeln!(output, "}}")?;
+    writeln!(output, "complete -F _sui_move_new sui")?;
+    Ok(())
+}
+
+/// Example modules that `sui move new --examples` adds to the package, each
+/// named after its template.
+pub(crate) const EXAMPLES: &[Template] = &[
+    Template {
+        name: "example_abilities",
+        description: "Structs with different abilities",
+        source: include_str!("templates/examples/abilities.move.tmpl"),
+        tests: include_str!("templates/examples/abilities_tests.move.tmpl"),
+    },
+    Template {
+        name: "example_entry_functions",
+        description: "Entry functions callable from transactions",
+        source: include_str!("templates/examples/entry_functions.move.tmpl"),
+        tests: include_str!("templates/examples/entry_functions_tests.move.tmpl"),
+    },
+    Template {
+        name: "example_events",
+        description: "Emitting events",
+        source: include_str!("templates/examples/events.move.tmpl"),
+        tests: include_str!("templates/examples/events_tests.move.tmpl"),
+    },
+];
+
+impl Template {
+    pub(crate) fn find(name: &str) -> anyhow::Result<&'static Template> {
+        TEMPLATES.iter().find(|t| t.name == name).ok_or_else(|| {
+            let available = template_names();
+            anyhow!(
+                "Unknown template {:?}, available templates are: {}",
+                name,
+                available.join(", ")
+            )
+        })
+    }
+
+    /// Instantiates the template for module `module_name` at named address `address_name`.
+    fn render(text: &str, address_name: &str, module_name: &str) -> String {
+        text.replace("{{address_name}}", address_name)
+            .replace("{{module_name}}", module_name)
+            .replace("{{witness_name}}", &module_name.to_ascii_uppercase())
+    }
+
+    /// Renders `sources/<module_name>.move` and, if `with_tests` is set,
+    /// `tests/<module_name>_tests.move`.
+    fn render_files(
+        &self,
+        address_name: &str,
+        module_name: &str,
+        with_tests: bool,
+    ) -> Vec<GeneratedFile> {
+        let tests_dir = with_tests.then_some(Path::new(TESTS_DIR));
+        self.render_files_in(Path::new(SOURCES_DIR), tests_dir, address_name, module_name)
+    }
+
+    /// Renders the example module `examples/<name>.move` and, if `with_tests` is
+    /// set, its tests alongside it so they are only built together.
+    fn render_example_files(&self, address_name: &str, with_tests: bool) -> Vec<GeneratedFile> {
+        let dir = Path::new(EXAMPLES_DIR);
+        self.render_files_in(dir, with_tests.then_some(dir), address_name, self.name)
+    }
+
+    fn render_files_in(
+        &self,
+        sources_dir: &Path,
+        tests_dir: Option<&Path>,
+        address_name: &str,
+        module_name: &str,
+    ) -> Vec<GeneratedFile> {
+        let mut files = vec![GeneratedFile {
+            path: sources_dir.join(format!("{}.move", module_name)),
+            contents: Self::render(self.source, address_name, module_name).into_bytes(),
+        }];
+        if let Some(tests_dir) = tests_dir {
+            files.push(GeneratedFile {
+                path: tests_dir.join(format!("{}_tests.move", module_name)),
+                contents: Self::render(self.tests, address_name, module_name).into_bytes(),
+            });
+        }
+        files
+    }
+}
+
+/// Copyright notice of the generated package.
+struct Copyright<'a> {
+    year: u32,
+    holder: &'a str,
+}
+
+/// Renders the comment lines to start every generated Move source with.
+fn source_header(copyright: Option<&Copyright>, license: Option<&License>) -> String {
+    let mut header = String::new();
+    if let Some(copyright) = copyright {
+        header.push_str(&format!(
+            "// Copyright (c) {}, {}\n",
+            copyright.year, copyright.holder
+        ));
+    }
+    if let Some(license) = license {
+        header.push_str(&format!("// SPDX-License-Identifier: {}\n", license.id));
+    }
+    if !header.is_empty() {
+        header.push('\n');
+    }
+    header
+}
+
+/// A license that `sui move new --license` can apply to the package.
+pub struct License {
+    /// SPDX identifier of the license.
+    pub id: &'static str,
+    text: &'static str,
+}
+
+pub(crate) const LICENSES: &[License] = &[
+    License {
+        id: "Apache-2.0",
+        text: include_str!("templates/licenses/Apache-2.0.tmpl"),
+    },
+    License {
+        id: "MIT",
+        text: include_str!("templates/licenses/MIT.tmpl"),
+    },
+];
+
+/// Passed to `--license` to not license the package.
+pub const NO_LICENSE: &str = "none";
+
+/// Identifiers accepted by `--license`, also offered as its completions.
+pub(crate) fn license_ids() -> Vec<&'static str> {
+    LICENSES
+        .iter()
+        .map(|l| l.id)
+        .chain(std::iter::once(NO_LICENSE))
+        .collect()
+}
+
+impl License {
+    /// The license text, crediting `copyright` where the license names its holder.
+    /// Without a copyright holder, the license's copyright line is left out, as it
+    /// is from the Move sources.
+    fn render(&self, copyright: Option<&Copyright>) -> String {
+        if let Some(copyright) = copyright {
+            return self
+                .text
+                .replace("<year>", &copyright.year.to_string())
+                .replace("<copyright holders>", copyright.holder);
+        }
+        let mut text = String::with_capacity(self.text.len());
+        let mut lines = self.text.split_inclusive('\n').peekable();
+        while let Some(line) = lines.next() {
+            if line.contains("<copyright holders>") {
+                // Along with the blank line separating it from the rest.
+                lines.next_if(|next| next.trim().is_empty());
+            } else {
+                text.push_str(line);
+            }
+        }
+        text
+    }
+
+    /// Looks up the license with SPDX identifier `id`, returning `None` for `none`.
+    pub(crate) fn find(id: &str) -> anyhow::Result<Option<&'static License>> {
+        if id == NO_LICENSE {
+            return Ok(None);
+        }
+        match LICENSES.iter().find(|l| l.id == id) {
+            Some(license) => Ok(Some(license)),
+            None => {
+                let available: Vec<_> = LICENSES.iter().map(|l| l.id).collect();
+                bail!(
+                    "Unsupported license {:?}, supported licenses are: {}, or {}",
+                    id,
+                    available.join(", "),
+                    NO_LICENSE
+                )
+            }
+        }
+    }
+}
+
+/// A CI service that `sui move new --ci` can generate a workflow for.
+pub struct CiProvider {
+    pub name: &'static str,
+    /// Location of the workflow, relative to the package root.
+    path: &'static str,
+    workflow: &'static str,
+}
+
+pub(crate) const CI_PROVIDERS: &[CiProvider] = &[CiProvider {
+    name: "github",
+    path: ".github/workflows/move.yml",
+    workflow: include_str!("templates/ci/github.yml.tmpl"),
+}];
+
+/// Passed to `--ci` to not generate a CI workflow.
+pub const NO_CI: &str = "none";
+
+/// Names accepted by `--ci`, also offered as its completions.
+pub(crate) fn ci_provider_names() -> Vec<&'static str> {
+    CI_PROVIDERS
+        .iter()
+        .map(|p| p.name)
+        .chain(std::iter::once(NO_CI))
+        .collect()
+}
+
+impl CiProvider {
+    /// Looks up the provider called `name`, returning `None` for `none`.
+    pub(crate) fn find(name: &str) -> anyhow::Result<Option<&'static CiProvider>> {
+        if name == NO_CI {
+            return Ok(None);
+        }
+        match CI_PROVIDERS.iter().find(|p| p.name == name) {
+            Some(provider) => Ok(Some(provider)),
+            None => {
+                let available: Vec<_> = CI_PROVIDERS.iter().map(|p| p.name).collect();
+                bail!(
+                    "Unsupported CI provider {:?}, supported providers are: {}, or {}",
+                    name,
+                    available.join(", "),
+                    NO_CI
+                )
+            }
+        }
+    }
+
+    /// Renders the workflow, installing the CLI from the same revision as `framework`.
+    fn render(&self, framework: &GitFramework) -> GeneratedFile {
+        GeneratedFile {
+            path: PathBuf::from(self.path),
+            contents: self
+                .workflow
+                .replace("{{sui_git_url}}", &framework.url)
+                .replace("{{framework_rev}}", &framework.rev)
+                .into_bytes(),
+        }
+    }
+}
+
+/// The release tag of the Sui repository matching the version of this CLI.
+pub fn default_framework_rev() -> String {
+    format!("devnet-{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// The year, in UTC, of `time`.
+fn year_of(time: SystemTime) -> u32 {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    // Howard Hinnant's `civil_from_days`, counting days from 0000-03-01 so that
+    // leap days end the year, in eras of 400 years of 146097 days.
+    let days = secs / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months are counted from March, so January and February are in the next year.
+    let month = (5 * day_of_year + 2) / 153;
+    (era * 400 + year_of_era + u64::from(month >= 10)) as u32
+}
+
+/// Describes how the framework revision `rev` may be incompatible with the CLI at
+/// `cli_version`, or `None` if it matches. Release tags (`devnet-0.8.0`, `v0.8.0`)
+/// are compared on their major and minor versions, and the version of a commit
+/// can't be told, so it is assumed to match. Any other revision names a branch,
+/// which moves independently of the CLI.
+fn version_mismatch(cli_version: &str, rev: &str) -> Option<String> {
+    let cli = semver::Version::parse(cli_version).ok()?;
+    let cli_short = format!("{}.{}", cli.major, cli.minor);
+    let tag = rev
+        .rsplit_once('-')
+        .map_or(rev, |(_, version)| version)
+        .trim_start_matches('v');
+    if let Ok(version) = semver::Version::parse(tag) {
+        return ((version.major, version.minor) != (cli.major, cli.minor)).then_some(format!(
+            "CLI is {} but framework rev {} is {}.{} — this may not compile",
+            cli_short, rev, version.major, version.minor
+        ));
+    }
+    let is_commit = (7..=40).contains(&rev.len()) && rev.chars().all(|c| c.is_ascii_hexdigit());
+    (!is_commit).then_some(format!(
+        "CLI is {} but framework rev is {} — this may not compile",
+        cli_short, rev
+    ))
+}
+
+/// Rejects revisions containing characters that would corrupt the generated manifest,
+/// or that git could take for an option.
+fn validate_framework_rev(rev: String) -> anyhow::Result<String> {
+    if rev.is_empty() {
+        bail!("Framework revision must not be empty");
+    }
+    if rev.starts_with('-') {
+        bail!(
+            "Invalid framework revision {:?}: revisions cannot start with '-'",
+            rev
+        );
+    }
+    if let Some(c) = rev
+        .chars()
+        .find(|c| matches!(c, '"' | '\'' | '{' | '}' | '\\') || c.is_control())
+    {
+        bail!(
+            "Invalid framework revision {:?}: character {:?} is not allowed",
+            rev,
+            c
+        );
+    }
+    Ok(rev)
+}
+
+/// Checks that `name` can be used as the framework's key in `[dependencies]`:
+/// a bare TOML key that is also a valid Move package name.
+fn validate_framework_dep_name(name: &str) -> anyhow::Result<String> {
+    // Move identifiers only use characters allowed in bare TOML keys.
+    if !identifier::is_valid(name) {
+        bail!(
+            "Invalid framework dependency name {:?}, expected a Move identifier such as {:?}",
+            name,
+            SUI_PKG_NAME
+        );
+    }
+    Ok(name.to_string())
+}
+
+/// Rejects git URLs that could be taken for a git option or corrupt the
+/// generated manifest.
+fn validate_framework_git(url: String) -> anyhow::Result<String> {
+    if url.is_empty() || url.starts_with('-') {
+        bail!("Invalid framework git URL {:?}", url);
+    }
+    if let Some(c) = url
+        .chars()
+        .find(|c| matches!(c, '"' | '\'' | '\\') || c.is_whitespace() || c.is_control())
+    {
+        bail!(
+            "Invalid framework git URL {:?}: character {:?} is not allowed",
+            url,
+            c
+        );
+    }
+    Ok(url)
+}
+
+/// Checks that `subdir` is a relative path, as git dependencies require.
+fn validate_framework_subdir(subdir: &str) -> anyhow::Result<String> {
+    // Leading separators are rejected on every platform, not just where they make a path absolute.
+    if subdir.is_empty()
+        || Path::new(subdir).is_absolute()
+        || subdir.starts_with(|c: char| c == '/' || c == '\\')
+    {
+        bail!(
+            "Invalid framework subdirectory {:?}, expected a path relative to the repository root such as {:?}",
+            subdir,
+            SUI_FRAMEWORK_SUBDIR
+        );
+    }
+    if subdir.contains(|c: char| c.is_control()) {
+        bail!(
+            "Invalid framework subdirectory {:?}: control characters are not allowed",
+            subdir
+        );
+    }
+    Ok(subdir.to_string())
+}
+
+/// Move editions a package can declare.
+pub const EDITIONS: &[&str] = &["legacy", "2024"];
+/// The edition of a manifest without an `edition` entry, as used by the bundled framework.
+pub const DEFAULT_EDITION: &str = "legacy";
+
+fn validate_edition(edition: &str) -> anyhow::Result<&str> {
+    if !EDITIONS.contains(&edition) {
+        bail!(
+            "Unknown edition {:?}, accepted editions are: {}",
+            edition,
+            EDITIONS.join(", ")
+        );
+    }
+    Ok(edition)
+}
+
+/// Asks for `label` on `output` until `input` provides an answer accepted by
+/// `validate`. An empty answer selects `default`, if any.
+fn prompt(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    label: &str,
+    default: Option<&str>,
+    validate: impl Fn(&str) -> anyhow::Result<()>,
+) -> anyhow::Result<String> {
+    loop {
+        match default {
+            Some(default) => write!(output, "{} [{}]: ", label, default)?,
+            None => write!(output, "{}: ", label)?,
+        }
+        output.flush()?;
+
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            bail!("No answer given for {:?}", label);
+        }
+        let answer = match (answer.trim(), default) {
+            ("", Some(default)) => default,
+            ("", None) => continue,
+            (answer, _) => answer,
+        };
+        match validate(answer) {
+            Ok(()) => return Ok(answer.to_string()),
+            Err(e) => writeln!(output, "{}", e)?,
+        }
+    }
+}
+
+/// Derives the module identifier from package `name`, which must be a valid
+/// Move identifier once lowercased.
+fn validate_package_name(name: &str) -> Result<String, NewError> {
+    let ident = name.to_lowercase();
+    if !identifier::is_valid(&ident) {
+        return Err(NewError::InvalidName(format!(
+            "Package name {:?} is not a valid Move identifier, try {:?} instead or pass \
+             --module-name to choose the module identifier separately",
+            name,
+            suggest_identifier(&ident)
+        )));
+    }
+    Ok(ident)
+}
+
+fn validate_module_name(module_name: &str) -> Result<String, NewError> {
+    if !identifier::is_valid(module_name) {
+        return Err(NewError::InvalidName(format!(
+            "Module name {:?} is not a valid Move identifier, try {:?} instead",
+            module_name,
+            suggest_identifier(module_name)
+        )));
+    }
+    Ok(module_name.to_string())
+}
+
+/// Turns `name` into a valid Move identifier by replacing illegal characters
+/// with `_` and prefixing names that don't start with a letter.
+fn suggest_identifier(name: &str) -> String {
+    let ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident
+    } else {
+        format!("pkg_{}", ident)
+    }
+}
+
+/// Parses `NAME=PATH` local dependencies of the package at `package_root`,
+/// checking that each `PATH` holds a package.
+fn parse_local_dependencies(
+    dependencies: &[String],
+    package_root: &Path,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut parsed = Vec::new();
+    for dependency in dependencies {
+        let (dep_name, dep_path) = dependency
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid dependency {:?}, expected NAME=PATH", dependency))?;
+        let (dep_name, dep_path) = (dep_name.trim(), dep_path.trim());
+        if !identifier::is_valid(dep_name) {
+            bail!(
+                "Invalid dependency {:?}: {:?} is not a valid package name, expected a Move identifier",
+                dependency,
+                dep_name
+            );
+        }
+        // The package root may not exist yet, so `..` is resolved without the filesystem.
+        let manifest = normalize_path(&package_root.join(dep_path)).join(MANIFEST_FILE);
+        if dep_path.is_empty() || !manifest.is_file() {
+            bail!(
+                "Invalid dependency {:?}: no {} found at {}",
+                dependency,
+                MANIFEST_FILE,
+                manifest.display()
+            );
+        }
+        parsed.push((dep_name.to_string(), dep_path.to_string()));
+    }
+    Ok(parsed)
+}
+
+/// Removes the `.` and `..` components of `path` without resolving symlinks.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(std::path::Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Parses `NAME=VALUE` named address assignments, rejecting invalid
+/// identifiers, invalid addresses and duplicate names.
+fn parse_named_addresses(assignments: &[String]) -> anyhow::Result<Vec<(String, String)>> {
+    let mut addresses: Vec<(String, String)> = Vec::new();
+    for assignment in assignments {
+        let (addr_name, addr_value) = assignment.split_once('=').ok_or_else(|| {
+            anyhow!(
+                "Invalid named address {:?}, expected NAME=VALUE",
+                assignment
+            )
+        })?;
+        let (addr_name, addr_value) = (addr_name.trim(), addr_value.trim());
+        if !identifier::is_valid(addr_name) {
+            bail!(
+                "Invalid named address {:?}: {:?} is not a valid Move identifier",
+                assignment,
+                addr_name
+            );
+        }
+        AccountAddress::from_hex_literal(addr_value).map_err(|_| {
+            anyhow!(
+                "Invalid named address {:?}: {:?} is not a valid address, expected a hex literal such as 0x42",
+                assignment,
+                addr_value
+            )
+        })?;
+        if addresses.iter().any(|(existing, _)| existing == addr_name) {
+            bail!("Named address {:?} is assigned more than once", addr_name);
+        }
+        addresses.push((addr_name.to_string(), addr_value.to_string()));
+    }
+    Ok(addresses)
+}
+
+/// Checks that `version` is a semantic version that Move manifests accept.
+fn validate_package_version(version: &str) -> anyhow::Result<String> {
+    let parsed = semver::Version::parse(version).with_context(|| {
+        format!(
+            "Invalid package version {:?}, expected a semantic version such as \"0.0.1\"",
+            version
+        )
+    })?;
+    if !parsed.pre.is_empty() || !parsed.build.is_empty() {
+        bail!(
+            "Invalid package version {:?}, Move packages only support MAJOR.MINOR.PATCH versions such as \"{}.{}.{}\"",
+            version,
+            parsed.major,
+            parsed.minor,
+            parsed.patch
+        );
+    }
+    Ok(parsed.to_string())
+}
+
+/// Builds a `local` dependency on the framework inside the Sui checkout at `sui_repo`.
+fn local_sui_pkg_path(sui_repo: &Path) -> anyhow::Result<String> {
+    let framework = sui_repo.join(SUI_FRAMEWORK_SUBDIR);
+    let framework = framework.canonicalize().with_context(|| {
+        format!(
+            "Cannot find the Sui framework at {}, is {} a checkout of the Sui repository?",
+            framework.display(),
+            sui_repo.display()
+        )
+    })?;
+    if !framework.join("Move.toml").is_file() {
+        bail!(
+            "No Move.toml found in {}, is {} a checkout of the Sui repository?",
+            framework.display(),
+            sui_repo.display()
+        );
+    }
+    Ok(format!(
+        "{{ local = \"{}\" }}",
+        escape_toml_str(&framework.to_string_lossy())
+    ))
+}
+
+/// Names of the addresses of the Sui framework and its standard library, for when
+/// their manifests can't be read.
+const RESERVED_ADDRESS_NAMES: &[&str] = &["std", "sui"];
+
+/// Collects the named addresses declared by the package at `package_dir` and by
+/// its local dependencies, such as the standard library of the Sui framework.
+fn local_framework_address_names(package_dir: &Path) -> anyhow::Result<BTreeSet<String>> {
+    let mut names = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![package_dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let manifest = fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Cannot read {}", manifest_path.display()))?;
+        let manifest: toml::Value = toml::from_str(&manifest)
+            .with_context(|| format!("Invalid {}", manifest_path.display()))?;
+        if let Some(addresses) = manifest.get("addresses").and_then(toml::Value::as_table) {
+            names.extend(addresses.keys().cloned());
+        }
+        let dependencies = manifest.get("dependencies").and_then(toml::Value::as_table);
+        for dependency in dependencies.into_iter().flat_map(|deps| deps.values()) {
+            if let Some(local) = dependency.get("local").and_then(toml::Value::as_str) {
+                let dependency_dir = dir.join(local);
+                if visited.insert(dependency_dir.clone()) {
+                    pending.push(dependency_dir);
+                }
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Escapes `s` for use inside a double-quoted TOML string.
+fn escape_toml_str(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }